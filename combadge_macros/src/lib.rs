@@ -1,30 +1,753 @@
 extern crate proc_macro;
 
+use std::collections::HashSet;
+
 use proc_macro::TokenStream;
-use quote::{format_ident, quote};
+use proc_macro2::{Group, Span, TokenStream as TokenStream2, TokenTree};
+use quote::{format_ident, quote, quote_spanned, ToTokens};
+use syn::meta::ParseNestedMeta;
+use syn::parse::Parser;
+use syn::punctuated::Punctuated;
+use syn::spanned::Spanned;
+use syn::visit_mut::{self, VisitMut};
 use syn::{
-    parse, parse_macro_input, FnArg, GenericArgument, Ident, ImplItem, Index, ItemImpl, ItemTrait,
-    LitInt, Pat, PathArguments, ReturnType, TraitItem, Type, TypeParamBound, Visibility,
+    parse, parse_macro_input, parse_quote, Expr, ExprPath, FnArg, GenericArgument, GenericParam,
+    Generics, Ident, ImplItem, ImplItemFn, Index, Item, ItemImpl, ItemStruct, ItemTrait, LitInt,
+    LitStr, Pat, PatIdent, PathArguments, PathSegment, ReturnType, Signature, Token,
+    TraitBoundModifier, TraitItem, TraitItemFn, Type, TypeParamBound, Visibility, WhereClause,
 };
 
-fn parse_count(item: TokenStream) -> usize {
-    let Ok(count) = parse::<LitInt>(item.clone().into()) else {
-        panic!("expected an integer literal");
-    };
-
-    let Ok(count) = count.base10_parse::<usize>() else {
-        panic!("failed to parse {count} as usize");
-    };
+fn parse_count(item: TokenStream) -> syn::Result<usize> {
+    let literal = parse::<LitInt>(item)?;
+    let count = literal.base10_parse::<usize>()?;
 
     if count == 0 {
-        panic!("must generate at least 1 variable");
+        return Err(syn::Error::new(
+            literal.span(),
+            "must generate at least 1 variable",
+        ));
     }
 
     if count > 26 {
-        panic!("can only generate up to 26 variables without running out of letters");
+        return Err(syn::Error::new(
+            literal.span(),
+            "can only generate up to 26 variables without running out of letters",
+        ));
+    }
+
+    Ok(count)
+}
+
+/// Checks at compile time that `ty` can be posted, reporting the failure at the type itself.
+fn assert_postable(ty: &impl ToTokens) -> TokenStream2 {
+    let message = format!(
+        "`{}` can't be posted: it must implement `Serialize` and `DeserializeOwned` or convert to and from `JsValue`",
+        ty.to_token_stream()
+    )
+    .replace('{', "{{")
+    .replace('}', "}}");
+
+    quote_spanned! { ty.span() =>
+        assert!(<#ty as ::combadge::Post>::POSTABLE, #message);
+    }
+}
+
+/// Whether any identifier in `tokens` is one of `idents`.
+fn mentions(tokens: TokenStream2, idents: &[Ident]) -> bool {
+    tokens.into_iter().any(|tree| match tree {
+        TokenTree::Ident(ident) => idents.contains(&ident),
+        TokenTree::Group(group) => mentions(group.stream(), idents),
+        _ => false,
+    })
+}
+
+/// Replaces each identifier in `tokens` that appears on the left of `renames` with its right.
+fn rename_idents(tokens: TokenStream2, renames: &[(Ident, Ident)]) -> TokenStream2 {
+    tokens
+        .into_iter()
+        .map(|tree| match tree {
+            TokenTree::Ident(ident) => renames
+                .iter()
+                .find(|(from, _)| *from == ident)
+                .map_or(TokenTree::Ident(ident), |(_, to)| {
+                    TokenTree::Ident(to.clone())
+                }),
+            TokenTree::Group(group) => {
+                let mut replaced =
+                    Group::new(group.delimiter(), rename_idents(group.stream(), renames));
+                replaced.set_span(group.span());
+                TokenTree::Group(replaced)
+            }
+            tree => tree,
+        })
+        .collect()
+}
+
+/// Replaces each `Self` in `tokens` with `replacement`.
+fn replace_self(tokens: TokenStream2, replacement: &TokenStream2) -> TokenStream2 {
+    tokens
+        .into_iter()
+        .flat_map(|tree| match tree {
+            TokenTree::Ident(ident) if ident == "Self" => replacement.clone(),
+            TokenTree::Group(group) => {
+                let mut replaced =
+                    Group::new(group.delimiter(), replace_self(group.stream(), replacement));
+                replaced.set_span(group.span());
+                TokenTree::Group(replaced).into()
+            }
+            tree => tree.into(),
+        })
+        .collect()
+}
+
+/// Converts an `UpperCamelCase` trait name to `snake_case`.
+fn snake_case(name: &Ident) -> String {
+    let mut snake = String::new();
+    for (index, char) in name.to_string().chars().enumerate() {
+        if char.is_uppercase() && index > 0 {
+            snake.push('_');
+        }
+        snake.extend(char.to_lowercase());
+    }
+    snake
+}
+
+/// Supertraits that are never `#[combadge]` traits, so they're left to the implementation.
+const LOCAL_SUPERTRAITS: &[&str] = &[
+    "Any",
+    "Clone",
+    "Copy",
+    "Debug",
+    "Default",
+    "Display",
+    "Eq",
+    "Hash",
+    "Ord",
+    "PartialEq",
+    "PartialOrd",
+    "Send",
+    "Sized",
+    "Sync",
+    "Unpin",
+];
+
+/// The generated items of a supertrait that is itself a `#[combadge]` trait.
+struct Supertrait {
+    trait_name: Ident,
+    client: TokenStream2,
+    batch: TokenStream2,
+    server: TokenStream2,
+    route: TokenStream2,
+    mirror: TokenStream2,
+}
+
+impl Supertrait {
+    /// Returns `None` for bounds that aren't remote traits, such as lifetimes and marker traits.
+    fn of(bound: &TypeParamBound) -> Option<Self> {
+        let TypeParamBound::Trait(bound) = bound else {
+            return None;
+        };
+        if !matches!(bound.modifier, TraitBoundModifier::None) {
+            return None;
+        }
+
+        let last = bound.path.segments.last()?;
+        let arguments = match &last.arguments {
+            PathArguments::None => Vec::new(),
+            PathArguments::AngleBracketed(arguments) => arguments.args.iter().collect(),
+            PathArguments::Parenthesized(_) => return None,
+        };
+        if LOCAL_SUPERTRAITS.contains(&last.ident.to_string().as_str()) {
+            return None;
+        }
+
+        let generated = |prefix: &str, suffix: &str| {
+            let mut path = bound.path.clone();
+            if let Some(last) = path.segments.last_mut() {
+                last.ident = format_ident!(
+                    "{}{}{}",
+                    prefix,
+                    last.ident,
+                    suffix,
+                    span = last.ident.span()
+                );
+                last.arguments = PathArguments::None;
+            }
+            path
+        };
+        let with_port = |suffix: &str| {
+            let path = generated("", suffix);
+            quote! { #path<P, #(#arguments),*> }
+        };
+        let mirror = generated("Async", "");
+
+        Some(Self {
+            trait_name: last.ident.clone(),
+            client: with_port("Client"),
+            batch: with_port("Batch"),
+            server: with_port("Server"),
+            route: generated("", "Route").to_token_stream(),
+            mirror: quote! { #mirror<#(#arguments),*> },
+        })
+    }
+}
+
+/// Supertraits that still let a trait be made into an object.
+const OBJECT_SUPERTRAITS: &[&str] = &["Any", "Debug", "Display", "Send", "Sync", "Unpin"];
+
+/// Whether `dyn Trait` can be named for `item`. This errs on the side of `false`, since naming it
+/// for a trait that can't be made into an object is an error. In particular, remote supertraits
+/// aren't checked, so traits with them are left out.
+fn is_dyn_compatible(item: &ItemTrait) -> bool {
+    let forbidden = [format_ident!("Self"), format_ident!("impl")];
+
+    let supertraits = item.supertraits.iter().all(|bound| match bound {
+        TypeParamBound::Lifetime(_) => true,
+        TypeParamBound::Trait(bound) => bound
+            .path
+            .segments
+            .last()
+            .is_some_and(|last| OBJECT_SUPERTRAITS.contains(&last.ident.to_string().as_str())),
+        _ => false,
+    });
+
+    let where_clause = item
+        .generics
+        .where_clause
+        .as_ref()
+        .is_none_or(|where_clause| !mentions(where_clause.to_token_stream(), &forbidden));
+
+    let items = item.items.iter().all(|trait_item| {
+        let TraitItem::Fn(function) = trait_item else {
+            return false;
+        };
+        let signature = &function.sig;
+        let by_reference = signature
+            .receiver()
+            .is_some_and(|receiver| receiver.reference.is_some() && receiver.colon_token.is_none());
+        let arguments = signature
+            .inputs
+            .iter()
+            .filter(|input| matches!(input, FnArg::Typed(_)));
+        let output = &signature.output;
+
+        by_reference
+            && signature.asyncness.is_none()
+            && signature.generics.type_params().next().is_none()
+            && signature.generics.const_params().next().is_none()
+            && signature.generics.where_clause.is_none()
+            && !mentions(quote! { #(#arguments)* #output }, &forbidden)
+    });
+
+    supertraits && where_clause && items
+}
+
+/// Converts a `snake_case` method name to `UpperCamelCase`.
+fn upper_camel(name: &Ident) -> String {
+    name.to_string()
+        .split('_')
+        .map(|word| {
+            let mut chars = word.chars();
+            chars.next().map_or_else(String::new, |first| {
+                first.to_uppercase().chain(chars).collect()
+            })
+        })
+        .collect()
+}
+
+/// The type and const parameters of a trait or method, split up so they can be carried onto the
+/// generated types. Lifetime parameters are left out.
+#[derive(Default)]
+struct GenericParts {
+    /// Parameters as declared, without bounds, such as `T` or `const N: usize`
+    declaration: Vec<TokenStream2>,
+    /// Parameters as passed, such as `T` or `N`
+    argument: Vec<Ident>,
+    type_param: Vec<Ident>,
+    /// Bounds from the parameter list and the where clause
+    predicate: Vec<TokenStream2>,
+}
+
+impl GenericParts {
+    fn of(generics: &Generics, renames: &[(Ident, Ident)]) -> Self {
+        let mut parts = Self::default();
+        for param in &generics.params {
+            match param {
+                GenericParam::Type(param) => {
+                    let ident = &param.ident;
+                    let bounds = &param.bounds;
+                    parts.declaration.push(quote! { #ident });
+                    if !bounds.is_empty() {
+                        parts.predicate.push(quote! { #ident: #bounds });
+                    }
+                    parts.argument.push(ident.clone());
+                    parts.type_param.push(ident.clone());
+                }
+                GenericParam::Const(param) => {
+                    let ident = &param.ident;
+                    let ty = &param.ty;
+                    parts.declaration.push(quote! { const #ident: #ty });
+                    parts.argument.push(ident.clone());
+                }
+                GenericParam::Lifetime(_) => {}
+            }
+        }
+        if let Some(where_clause) = &generics.where_clause {
+            parts.predicate.extend(
+                where_clause
+                    .predicates
+                    .iter()
+                    .map(ToTokens::to_token_stream),
+            );
+        }
+
+        parts.declaration = parts
+            .declaration
+            .into_iter()
+            .map(|tokens| rename_idents(tokens, renames))
+            .collect();
+        parts.predicate = parts
+            .predicate
+            .into_iter()
+            .map(|tokens| rename_idents(tokens, renames))
+            .collect();
+        for ident in parts.argument.iter_mut().chain(&mut parts.type_param) {
+            if let Some((_, to)) = renames.iter().find(|(from, _)| from == ident) {
+                *ident = to.clone();
+            }
+        }
+        parts
+    }
+
+    fn extend(&mut self, other: Self) {
+        self.declaration.extend(other.declaration);
+        self.argument.extend(other.argument);
+        self.type_param.extend(other.type_param);
+        self.predicate.extend(other.predicate);
+    }
+
+    /// Bounds every type parameter by `'static`, and those that are sent or received by `Post` too.
+    fn post_predicates(&self, sent: &[TokenStream2]) -> Vec<TokenStream2> {
+        self.type_param
+            .iter()
+            .map(|param| {
+                let param_slice = std::slice::from_ref(param);
+                if sent.iter().any(|ty| mentions(ty.clone(), param_slice)) {
+                    quote! { #param: ::combadge::Post + 'static }
+                } else {
+                    quote! { #param: 'static }
+                }
+            })
+            .collect()
+    }
+}
+
+/// Names each non-receiver argument so that generated code can forward it, keeping the name from
+/// the signature when the argument is bound to a plain identifier.
+fn argument_names(signature: &Signature) -> Vec<Ident> {
+    signature
+        .inputs
+        .iter()
+        .filter_map(|input| match input {
+            FnArg::Receiver(_) => None,
+            FnArg::Typed(typed) => Some(typed),
+        })
+        .enumerate()
+        .map(|(index, typed)| match typed.pat.as_ref() {
+            Pat::Ident(PatIdent {
+                ident,
+                subpat: None,
+                ..
+            }) => ident.clone(),
+            _ => format_ident!("argument{}_", index),
+        })
+        .collect()
+}
+
+/// Returns `signature` with each argument bound to its name from [`argument_names`] in place of
+/// whatever pattern it had.
+fn with_argument_names(signature: &Signature) -> Signature {
+    let mut signature = signature.clone();
+    let names = argument_names(&signature);
+    let typed = signature.inputs.iter_mut().filter_map(|input| match input {
+        FnArg::Receiver(_) => None,
+        FnArg::Typed(typed) => Some(typed),
+    });
+    for (typed, name) in typed.zip(names) {
+        *typed.pat = parse_quote! { #name };
+    }
+    signature
+}
+
+/// Returns the type a borrowed argument refers to, which is sent as its `ToOwned::Owned` type, or
+/// `None` if the argument is owned and sent as it is.
+fn borrowed_referent(ty: &Type) -> syn::Result<Option<Type>> {
+    match ty {
+        Type::Reference(reference) if reference.mutability.is_some() => Err(syn::Error::new_spanned(
+            ty,
+            "can't pass a mutable reference to a remote method, since changes can't be sent back",
+        )),
+        Type::Reference(reference) => Ok(Some((*reference.elem).clone())),
+        Type::Group(group) => borrowed_referent(&group.elem),
+        Type::Paren(paren) => borrowed_referent(&paren.elem),
+        _ => Ok(None),
+    }
+}
+
+/// 64-bit FNV-1a, used instead of `DefaultHasher` so that signatures are stable across compilers
+fn fnv1a(text: &str) -> u64 {
+    text.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Options set with `#[combadge(...)]` on a trait method.
+#[derive(Default)]
+struct MethodOptions {
+    id: Option<LitInt>,
+    concurrency: Option<TokenStream2>,
+    local: bool,
+}
+
+impl MethodOptions {
+    fn parse(function: &TraitItemFn) -> syn::Result<Self> {
+        let mut options = Self::default();
+        for attribute in &function.attrs {
+            if !attribute.path().is_ident("combadge") {
+                continue;
+            }
+
+            attribute.parse_nested_meta(|meta| {
+                if meta.path.is_ident("id") {
+                    let id = meta.value()?.parse::<LitInt>()?;
+                    id.base10_parse::<u32>()?;
+                    options.id = Some(id);
+                    Ok(())
+                } else if meta.path.is_ident("concurrency") {
+                    options.concurrency = Some(parse_concurrency(&meta)?);
+                    Ok(())
+                } else if meta.path.is_ident("local") {
+                    if function.default.is_none() {
+                        return Err(meta.error("local methods need a default implementation"));
+                    }
+                    options.local = true;
+                    Ok(())
+                } else {
+                    Err(meta.error("unsupported combadge attribute"))
+                }
+            })?;
+        }
+
+        if options.local && (options.id.is_some() || options.concurrency.is_some()) {
+            return Err(syn::Error::new_spanned(
+                &function.sig.ident,
+                "local methods aren't called remotely, so they can't have an ID or concurrency",
+            ));
+        }
+        Ok(options)
+    }
+}
+
+/// Rewrites the body of a local method to run against the generated client, awaiting each call
+/// to a method of the trait and propagating its errors, and wrapping returned values in `Ok`.
+struct ClientBody<'a> {
+    trait_name: &'a Ident,
+    methods: &'a [Ident],
+}
+
+impl ClientBody<'_> {
+    fn is_method_call(&self, expr: &Expr) -> bool {
+        match expr {
+            Expr::MethodCall(call) => {
+                is_self(&call.receiver) && self.methods.contains(&call.method)
+            }
+            Expr::Call(call) => {
+                call.args.first().is_some_and(is_self) && self.path_method(&call.func).is_some()
+            }
+            _ => false,
+        }
+    }
+
+    /// The method named by `Self::method`, `Trait::method` or `<Self as Trait>::method`, which
+    /// the client has as its own.
+    fn path_method<'e>(&self, func: &'e Expr) -> Option<&'e PathSegment> {
+        let Expr::Path(ExprPath { qself, path, .. }) = func else {
+            return None;
+        };
+        let [owner, method] = path.segments.iter().collect::<Vec<_>>()[..] else {
+            return None;
+        };
+        let owner_is_trait = owner.ident == *self.trait_name;
+        let callable = qself.as_ref().map_or(
+            owner.ident == "Self" || owner_is_trait,
+            |qself| {
+                owner_is_trait
+                    && matches!(&*qself.ty, Type::Path(ty) if ty.qself.is_none() && ty.path.is_ident("Self"))
+            },
+        );
+        (callable && path.leading_colon.is_none() && self.methods.contains(&method.ident))
+            .then_some(method)
+    }
+
+    /// Calls the client's own method for a method called through a path.
+    fn call_on_client(&self, expr: &mut Expr) {
+        if let Expr::Call(call) = expr {
+            if let Some(method) = self.path_method(&call.func).cloned() {
+                call.func = parse_quote! { Self::#method };
+            }
+        }
+    }
+}
+
+fn is_self(expr: &Expr) -> bool {
+    matches!(expr, Expr::Path(path) if path.qself.is_none() && path.path.is_ident("self"))
+}
+
+impl VisitMut for ClientBody<'_> {
+    fn visit_expr_mut(&mut self, expr: &mut Expr) {
+        match expr {
+            // Closures and async blocks can't return from the method or propagate its errors
+            Expr::Closure(_) | Expr::Async(_) => {}
+            // Calls to async methods are already awaited
+            Expr::Await(awaited) if self.is_method_call(&awaited.base) => {
+                visit_mut::visit_expr_mut(self, &mut awaited.base);
+                self.call_on_client(&mut awaited.base);
+                *expr = parse_quote! { #awaited? };
+            }
+            _ if self.is_method_call(expr) => {
+                visit_mut::visit_expr_mut(self, expr);
+                self.call_on_client(expr);
+                *expr = parse_quote! { #expr.await? };
+            }
+            Expr::Return(returned) => {
+                visit_mut::visit_expr_return_mut(self, returned);
+                let value = returned
+                    .expr
+                    .take()
+                    .map_or_else(|| quote! { () }, ToTokens::into_token_stream);
+                returned.expr = Some(parse_quote! { Ok(#value) });
+            }
+            _ => visit_mut::visit_expr_mut(self, expr),
+        }
+    }
+
+    fn visit_item_mut(&mut self, _item: &mut Item) {}
+}
+
+/// Parses `concurrency = "serial"`, `concurrency = "concurrent"` or `concurrency = N` into a
+/// `Concurrency` expression.
+fn parse_concurrency(meta: &ParseNestedMeta) -> syn::Result<TokenStream2> {
+    let value = meta.value()?;
+    if value.peek(LitInt) {
+        let permits = value.parse::<LitInt>()?;
+        if permits.base10_parse::<usize>()? == 0 {
+            return Err(syn::Error::new(
+                permits.span(),
+                "concurrency must allow at least one call",
+            ));
+        }
+        return Ok(quote! { ::combadge::Concurrency::Bounded(#permits) });
+    }
+
+    let policy = value.parse::<LitStr>()?;
+    match policy.value().as_str() {
+        "serial" => Ok(quote! { ::combadge::Concurrency::Serial }),
+        "concurrent" => Ok(quote! { ::combadge::Concurrency::Concurrent }),
+        _ => Err(syn::Error::new(
+            policy.span(),
+            "expected \"serial\", \"concurrent\" or a number of concurrent calls",
+        )),
+    }
+}
+
+/// Assigns each function a procedure ID, honoring explicit `#[combadge(id = N)]` attributes and
+/// numbering the rest in declaration order, skipping IDs that were claimed explicitly.
+fn procedure_ids(options: &[MethodOptions]) -> syn::Result<Vec<u32>> {
+    let mut claimed = HashSet::new();
+    let mut explicit = Vec::with_capacity(options.len());
+    for options in options {
+        let id = options
+            .id
+            .as_ref()
+            .map(|literal| {
+                let id = literal.base10_parse::<u32>()?;
+                if !claimed.insert(id) {
+                    return Err(syn::Error::new(
+                        literal.span(),
+                        format!("procedure ID {id} is already in use"),
+                    ));
+                }
+                Ok(id)
+            })
+            .transpose()?;
+        explicit.push(id);
+    }
+
+    let mut next = 0;
+    Ok(explicit
+        .into_iter()
+        .map(|id| {
+            id.unwrap_or_else(|| {
+                while claimed.contains(&next) {
+                    next += 1;
+                }
+                claimed.insert(next);
+                next
+            })
+        })
+        .collect())
+}
+
+/// How a method's return value is turned into the value sent back to the caller.
+enum ReturnKind {
+    /// A plain value, which the server can send as soon as the method returns.
+    Owned(Type),
+    /// A future that borrows `self` (from an `async fn` or `impl Future`), so the server has to
+    /// keep the local value borrowed until it completes.
+    Borrowing(Type),
+    /// A boxed `dyn Future`, which may be pinned and carry auto traits. Unless it's bound by a
+    /// lifetime other than `'static`, it doesn't borrow `self`, so the server awaits it after
+    /// releasing the local value.
+    Boxed {
+        output: Type,
+        pinned: bool,
+        borrowing: bool,
+    },
+}
+
+impl ReturnKind {
+    fn of(signature: &Signature) -> Self {
+        let output = match &signature.output {
+            ReturnType::Default => parse_quote! { () },
+            ReturnType::Type(_, t) => t.as_ref().clone(),
+        };
+
+        if signature.asyncness.is_some() {
+            return Self::Borrowing(output);
+        }
+
+        if let Type::ImplTrait(impl_trait) = &output {
+            if let Some(future_output) = future_output(&impl_trait.bounds) {
+                return Self::Borrowing(future_output.clone());
+            }
+        }
+
+        // Box<dyn Future<Output = T>> or Pin<Box<dyn Future<Output = T>>>, alongside any auto traits
+        // and a lifetime
+        let pinned = single_type_argument(&output, "Pin");
+        let boxed = pinned.unwrap_or(&output);
+        if let Some(Type::TraitObject(trait_object)) = single_type_argument(boxed, "Box") {
+            if let Some(future_output) = future_output(&trait_object.bounds) {
+                let borrowing = trait_object.bounds.iter().any(|bound| {
+                    matches!(bound, TypeParamBound::Lifetime(lifetime) if lifetime.ident != "static")
+                });
+                return Self::Boxed {
+                    output: future_output.clone(),
+                    pinned: pinned.is_some(),
+                    borrowing,
+                };
+            }
+        }
+
+        Self::Owned(output)
+    }
+
+    /// The type the caller receives.
+    fn output(&self) -> TokenStream2 {
+        match self {
+            Self::Owned(t) | Self::Borrowing(t) | Self::Boxed { output: t, .. } => quote! { #t },
+        }
+    }
+
+    /// Whether the server has to keep the local value borrowed until the result is ready.
+    const fn is_borrowing(&self) -> bool {
+        match self {
+            Self::Owned(_) => false,
+            Self::Borrowing(_) => true,
+            Self::Boxed { borrowing, .. } => *borrowing,
+        }
+    }
+
+    /// Turns the result of `call` into something `MaybeAsync` accepts if the value is owned, or
+    /// into a future if it borrows `self`. Boxed futures lose their auto traits on the way. `output`
+    /// spells the type the caller receives where the call is made.
+    fn adapt(&self, call: TokenStream2, output: &TokenStream2) -> TokenStream2 {
+        let Self::Boxed {
+            pinned, borrowing, ..
+        } = self
+        else {
+            return call;
+        };
+
+        let pinned_call = if *pinned {
+            call
+        } else {
+            quote! { ::std::boxed::Box::into_pin(#call) }
+        };
+        if *borrowing {
+            pinned_call
+        } else {
+            quote! {
+                {
+                    let future: ::std::pin::Pin<::std::boxed::Box<dyn ::std::future::Future<Output = #output>>> = #pinned_call;
+                    future
+                }
+            }
+        }
+    }
+}
+
+/// Returns `T` if `ty` is spelled `{name}<T>`.
+fn single_type_argument<'a>(ty: &'a Type, name: &str) -> Option<&'a Type> {
+    let Type::Path(path) = ty else {
+        return None;
+    };
+
+    if path.qself.is_some() {
+        return None;
+    }
+
+    let segment = path.path.segments.last()?;
+    if segment.ident != name {
+        return None;
+    }
+
+    let PathArguments::AngleBracketed(arguments) = &segment.arguments else {
+        return None;
+    };
+
+    match arguments.args.first()? {
+        GenericArgument::Type(argument) if arguments.args.len() == 1 => Some(argument),
+        _ => None,
     }
+}
+
+/// Returns `T` if `bounds` include `Future<Output = T>`.
+fn future_output(bounds: &Punctuated<TypeParamBound, Token![+]>) -> Option<&Type> {
+    bounds.iter().find_map(|bound| {
+        let TypeParamBound::Trait(bound) = bound else {
+            return None;
+        };
+
+        let segment = bound.path.segments.last()?;
+        if segment.ident != "Future" {
+            return None;
+        }
+
+        let PathArguments::AngleBracketed(arguments) = &segment.arguments else {
+            return None;
+        };
+
+        arguments.args.iter().find_map(|argument| match argument {
+            GenericArgument::AssocType(assoc) if assoc.ident == "Output" => Some(&assoc.ty),
+            _ => None,
+        })
+    })
+}
 
-    count
+/// Returns `T` if `ty` is spelled `Handle<T>`.
+fn handle_target(ty: &Type) -> Option<&Type> {
+    single_type_argument(ty, "Handle")
 }
 
 fn build_variables(count: usize) -> (Vec<Ident>, Vec<Ident>) {
@@ -47,7 +770,10 @@ fn build_variables(count: usize) -> (Vec<Ident>, Vec<Ident>) {
 
 #[proc_macro]
 pub fn build_call_traits(item: TokenStream) -> TokenStream {
-    let max_count = parse_count(item);
+    let max_count = match parse_count(item) {
+        Ok(max_count) => max_count,
+        Err(error) => return error.to_compile_error().into(),
+    };
 
     let mut call_traits = quote! {};
     for count in 1..=max_count {
@@ -96,7 +822,10 @@ pub fn build_call_traits(item: TokenStream) -> TokenStream {
 
 #[proc_macro]
 pub fn build_callback_from_closure(item: TokenStream) -> TokenStream {
-    let max_count = parse_count(item);
+    let max_count = match parse_count(item) {
+        Ok(max_count) => max_count,
+        Err(error) => return error.to_compile_error().into(),
+    };
 
     let mut callback_from_closure = quote! {};
     for count in 1..=max_count {
@@ -108,9 +837,11 @@ pub fn build_callback_from_closure(item: TokenStream) -> TokenStream {
             impl<#(#type_name),*, Return> From<Box<dyn Fn(#(#type_name),*) -> Return>> for Callback<(#(#type_name),*,), Return> {
                 fn from(callback: Box<dyn Fn(#(#type_name),*) -> Return>) -> Self {
                     Self {
-                        local: Some(callback),
+                        local: Some(std::rc::Rc::new(callback)),
                         async_local: None,
                         remote: None,
+                        forwarding: None,
+                        revoker: None,
                     }
                 }
             }
@@ -119,8 +850,10 @@ pub fn build_callback_from_closure(item: TokenStream) -> TokenStream {
                 fn from(callback: Box<dyn Fn(#(#type_name),*) -> AsyncReturn<Return>>) -> Self {
                     Self {
                         local: None,
-                        async_local: Some(callback),
+                        async_local: Some(std::rc::Rc::new(callback)),
                         remote: None,
+                        forwarding: None,
+                        revoker: None,
                     }
                 }
             }
@@ -130,7 +863,9 @@ pub fn build_callback_from_closure(item: TokenStream) -> TokenStream {
                     Self {
                         local: None,
                         async_local: None,
-                        remote: Some(callback),
+                        remote: Some(std::rc::Rc::new(callback)),
+                        forwarding: None,
+                        revoker: None,
                     }
                 }
             }
@@ -142,7 +877,10 @@ pub fn build_callback_from_closure(item: TokenStream) -> TokenStream {
 
 #[proc_macro]
 pub fn build_callback_types(item: TokenStream) -> TokenStream {
-    let max_count = parse_count(item);
+    let max_count = match parse_count(item) {
+        Ok(max_count) => max_count,
+        Err(error) => return error.to_compile_error().into(),
+    };
 
     let mut callback_types = quote! {};
     for count in 1..=max_count {
@@ -164,7 +902,10 @@ pub fn build_callback_types(item: TokenStream) -> TokenStream {
 
 #[proc_macro]
 pub fn build_post_tuple(item: TokenStream) -> TokenStream {
-    let max_count = parse_count(item);
+    let max_count = match parse_count(item) {
+        Ok(max_count) => max_count,
+        Err(error) => return error.to_compile_error().into(),
+    };
 
     let mut post_tuple = quote! {};
     for count in 1..=max_count {
@@ -190,7 +931,10 @@ pub fn build_post_tuple(item: TokenStream) -> TokenStream {
 
 #[proc_macro]
 pub fn build_responder(item: TokenStream) -> TokenStream {
-    let max_count = parse_count(item);
+    let max_count = match parse_count(item) {
+        Ok(max_count) => max_count,
+        Err(error) => return error.to_compile_error().into(),
+    };
 
     let mut responder = quote! {};
     for count in 1..=max_count {
@@ -257,7 +1001,10 @@ pub fn build_responder(item: TokenStream) -> TokenStream {
 
 #[proc_macro]
 pub fn build_to_closure(item: TokenStream) -> TokenStream {
-    let max_count = parse_count(item);
+    let max_count = match parse_count(item) {
+        Ok(max_count) => max_count,
+        Err(error) => return error.to_compile_error().into(),
+    };
 
     let mut to_closure = quote! {};
     for count in 1..=max_count {
@@ -279,371 +1026,2392 @@ pub fn build_to_closure(item: TokenStream) -> TokenStream {
 }
 
 #[proc_macro_attribute]
-pub fn combadge(_attr: TokenStream, item: TokenStream) -> TokenStream {
+pub fn combadge(attr: TokenStream, item: TokenStream) -> TokenStream {
     let item: ItemTrait = parse_macro_input!(item);
-    let trait_name = item.ident.clone();
-
-    let functions = item
-        .items
-        .iter()
-        .filter_map(|item| match item {
-            TraitItem::Fn(f) => Some(f),
-            _ => None,
-        })
-        .collect::<Vec<_>>();
+    expand_combadge(attr, item)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
 
-    let name = functions
-        .iter()
-        .map(|function| function.sig.ident.clone())
-        .collect::<Vec<_>>();
+fn expand_combadge(attr: TokenStream, mut item: ItemTrait) -> syn::Result<TokenStream2> {
+    let mut trait_concurrency = quote! { ::combadge::Concurrency::Concurrent };
+    let attribute_parser = syn::meta::parser(|meta| {
+        if meta.path.is_ident("concurrency") {
+            trait_concurrency = parse_concurrency(&meta)?;
+            Ok(())
+        } else {
+            Err(meta.error("unsupported combadge attribute"))
+        }
+    });
+    attribute_parser.parse(attr)?;
+
+    let remote = RemoteTrait::new(&item, trait_concurrency)?;
+    let generated = [
+        remote.shared_impl(),
+        remote.dyn_handle(),
+        remote.postable(),
+        remote.client(),
+        remote.client_constructors(),
+        remote.client_calls(),
+        remote.client_local(),
+        remote.batch(),
+        remote.mirror(),
+        remote.local_mirror(),
+        remote.routing(),
+        remote.batched(),
+        remote.server(),
+        remote.server_procedures(),
+    ];
+
+    // Strip our helper attributes so they aren't expanded as attribute macros on the trait items
+    for trait_item in &mut item.items {
+        if let TraitItem::Fn(function) = trait_item {
+            function
+                .attrs
+                .retain(|attribute| !attribute.path().is_ident("combadge"));
+        }
+    }
 
-    let name_string = name.iter().map(|name| name.to_string()).collect::<Vec<_>>();
+    let result = quote! {
+        #item
+        #(#generated)*
+    };
 
-    let argument = functions
-        .iter()
-        .map(|function| function.sig.inputs.iter().collect::<Vec<_>>())
-        .collect::<Vec<_>>();
+    // println!("{}", prettyplease::unparse(&syn::parse2(result.clone()).unwrap()));
 
-    let non_receiver = argument
-        .iter()
-        .enumerate()
-        .map(|(index, arguments)| {
-            let non_receiver = arguments
-                .iter()
-                .filter_map(|arg| match arg {
-                    FnArg::Receiver(_) => None,
-                    FnArg::Typed(typed) => Some(typed.clone()),
-                })
-                .collect::<Vec<_>>();
+    Ok(result)
+}
 
-            if non_receiver.len() == arguments.len() {
-                panic!(
-                    "expected {} to have a receiver (self parameter)",
-                    name[index]
-                )
-            }
+/// A `#[combadge]` trait, along with what the generated code needs to know about it. Each part of
+/// the generated code comes from its own method.
+struct RemoteTrait<'a> {
+    item: &'a ItemTrait,
+    concurrency: TokenStream2,
+    supertraits: Vec<Supertrait>,
+    parts: GenericParts,
+    /// The trait as named in bounds, such as `Trait<T>`
+    path: TokenStream2,
+    /// The trait's own bounds, plus `Post` bounds for the parameters its methods send
+    client_predicate: Vec<TokenStream2>,
+    methods: Methods<'a>,
+    local: LocalMethods<'a>,
+    client_name: Ident,
+    batch_name: Ident,
+    server_name: Ident,
+    async_name: Ident,
+    route_name: Ident,
+    batched_name: Ident,
+}
 
-            non_receiver
+impl<'a> RemoteTrait<'a> {
+    fn new(item: &'a ItemTrait, concurrency: TokenStream2) -> syn::Result<Self> {
+        let options = item
+            .items
+            .iter()
+            .filter_map(|item| match item {
+                TraitItem::Fn(f) => Some(MethodOptions::parse(f).map(|options| (f, options))),
+                _ => None,
+            })
+            .collect::<syn::Result<Vec<_>>>()?;
+
+        // Local methods run their default implementation on whichever side they're called from, so
+        // the client gets its own copy instead of making a round trip
+        let (local_functions, options): (Vec<_>, Vec<_>) =
+            options.into_iter().partition(|(_, options)| options.local);
+        let (functions, options): (Vec<_>, Vec<_>) = options.into_iter().unzip();
+        let local_functions = local_functions
+            .into_iter()
+            .map(|(function, _)| function)
+            .collect::<Vec<_>>();
+
+        check_generics(item, &functions)?;
+        let methods = Methods::of(functions, options)?;
+        let local = LocalMethods::of(local_functions)?;
+
+        // Calls to supertraits' methods are routed to the supertraits' servers, and their clients
+        // are reachable from ours
+        let supertraits = item
+            .supertraits
+            .iter()
+            .filter_map(Supertrait::of)
+            .collect::<Vec<_>>();
+
+        let parts = GenericParts::of(&item.generics, &[]);
+        let trait_name = &item.ident;
+        let (_, trait_type_generics, _) = item.generics.split_for_impl();
+        let sent_by_trait = methods
+            .sent_type
+            .iter()
+            .flatten()
+            .cloned()
+            .collect::<Vec<_>>();
+        let client_predicate = parts
+            .predicate
+            .iter()
+            .cloned()
+            .chain(parts.post_predicates(&sent_by_trait))
+            .collect::<Vec<_>>();
+
+        Ok(Self {
+            item,
+            concurrency,
+            supertraits,
+            parts,
+            path: quote! { #trait_name #trait_type_generics },
+            client_predicate,
+            methods,
+            local,
+            client_name: format_ident!("{}Client", trait_name),
+            batch_name: format_ident!("{}Batch", trait_name),
+            server_name: format_ident!("{}Server", trait_name),
+            async_name: format_ident!("Async{}", trait_name),
+            route_name: format_ident!("{}Route", trait_name),
+            batched_name: format_ident!("Batched{}", trait_name),
         })
-        .collect::<Vec<_>>();
+    }
 
-    let non_receiver_name = non_receiver
-        .iter()
-        .map(|non_receiver| {
-            non_receiver
+    fn supertrait_fields(&self) -> Vec<Ident> {
+        (0..self.supertraits.len())
+            .map(|index| format_ident!("supertrait_{}", index))
+            .collect()
+    }
+
+    fn supertrait_indices(&self) -> Vec<u32> {
+        (0..self.supertraits.len())
+            .map(|index| u32::try_from(index).unwrap_or(u32::MAX))
+            .collect()
+    }
+
+    /// Hashes the methods' IDs and signatures, so that a client and server only match if they were
+    /// generated from the same trait.
+    fn signature(&self) -> u64 {
+        let Methods {
+            functions,
+            id,
+            non_receiver_type,
+            internal_type,
+            ..
+        } = &self.methods;
+        fnv1a(
+            &functions
                 .iter()
-                .filter_map(|item| match item.pat.as_ref() {
-                    Pat::Ident(ident) => Some(ident.clone()),
-                    _ => None,
+                .zip(id)
+                .zip(non_receiver_type)
+                .zip(internal_type)
+                .map(|(((function, id), non_receiver_type), internal_type)| {
+                    let name = &function.sig.ident;
+                    let generics = &function.sig.generics;
+                    format!(
+                        "{id}:{}",
+                        quote! { #name #generics(#(#non_receiver_type),*) -> #internal_type }
+                    )
                 })
                 .collect::<Vec<_>>()
-        })
-        .collect::<Vec<_>>();
+                .join(";"),
+        )
+    }
 
-    let non_receiver_type = non_receiver
-        .iter()
-        .map(|non_receiver| {
-            non_receiver
-                .iter()
-                .map(|item| item.ty.clone())
-                .collect::<Vec<_>>()
-        })
-        .collect::<Vec<_>>();
+    /// The signature checked in the handshake. Generic parameters are hashed by name in
+    /// `SIGNATURE`, so the types the trait is instantiated with are folded in when it's checked,
+    /// along with those of its supertraits.
+    fn instantiated_signature(&self, generated: fn(&Supertrait) -> &TokenStream2) -> TokenStream2 {
+        let signature = self.parts.type_param.iter().fold(
+            quote! { Self::SIGNATURE },
+            |signature, param| quote! { ::combadge::instantiate_signature::<#param>(#signature) },
+        );
+        self.supertraits
+            .iter()
+            .fold(signature, |signature, supertrait| {
+                let generated = generated(supertrait);
+                quote! { ::combadge::extend_signature(#signature, <#generated>::signature()) }
+            })
+    }
 
-    let output = functions
-        .iter()
-        .map(|function| function.sig.output.clone())
-        .collect::<Vec<_>>();
+    fn generic_arguments(&self, method_parts: &GenericParts) -> Vec<Ident> {
+        [&self.parts.argument[..], &method_parts.argument[..]].concat()
+    }
 
-    let internal_type = output
-        .iter()
-        .map(|output| match output {
-            ReturnType::Default => quote! { () },
-            ReturnType::Type(_, t) => match t.as_ref() {
-                Type::Path(path) => {
-                    if path.path.segments.len() > 1
-                        || path.path.segments.get(0).unwrap().ident != "Box"
-                    {
-                        return quote! { #t };
-                    }
-                    let segment = path.path.segments.get(0).unwrap();
-                    match &segment.arguments {
-                        PathArguments::AngleBracketed(arguments) => {
-                            if arguments.args.len() > 1 {
-                                return quote! { #t };
-                            }
-                            let argument = arguments.args.get(0).unwrap();
-                            match argument {
-                                GenericArgument::Type(generic_type) => match generic_type {
-                                    Type::TraitObject(trait_) => {
-                                        if trait_.dyn_token.is_none() || trait_.bounds.len() > 1 {
-                                            return quote! { #t };
-                                        }
-
-                                        match trait_.bounds.get(0).unwrap() {
-                                            TypeParamBound::Trait(bound) => {
-                                                if bound.path.segments.len() > 1 {
-                                                    return quote! { #t };
-                                                }
-
-                                                let segment = bound.path.segments.get(0).unwrap();
-                                                if segment.ident != "Future" {
-                                                    return quote! { #t };
-                                                }
-
-                                                if let PathArguments::AngleBracketed(arguments) =
-                                                    &segment.arguments
-                                                {
-                                                    if arguments.args.len() > 1 {
-                                                        return quote! { #t };
-                                                    }
-
-                                                    match arguments.args.get(0).unwrap() {
-                                                        GenericArgument::AssocType(assoc) => {
-                                                            if assoc.ident != "Output" {
-                                                                return quote! { #t };
-                                                            }
-
-                                                            let generic_type = &assoc.ty;
-                                                            quote! { #generic_type }
-                                                        }
-                                                        _ => quote! { #t },
-                                                    }
-                                                } else {
-                                                    quote! { #t }
-                                                }
-                                            }
-                                            _ => quote! { #t },
-                                        }
-                                    }
-                                    _ => quote! { #t },
-                                },
-                                _ => quote! { #t},
-                            }
-                        }
-                        _ => quote! { #t },
-                    }
-                }
-                _ => quote! { #t },
-            },
-        })
-        .collect::<Vec<_>>();
+    /// The types each method sends that involve generic parameters, which can only be checked to
+    /// be postable once the parameters are known.
+    fn generic_sent_type(&self) -> Vec<Vec<TokenStream2>> {
+        self.methods
+            .sent_type
+            .iter()
+            .zip(&self.methods.method_parts)
+            .map(|(sent_type, method_parts)| {
+                let generic = self.generic_arguments(method_parts);
+                sent_type
+                    .iter()
+                    .filter(|ty| mentions((*ty).clone(), &generic))
+                    .cloned()
+                    .collect()
+            })
+            .collect()
+    }
 
-    let client_name = format_ident!("{}Client", item.ident);
-    let client = quote! {
-        #[derive(Clone, Debug)]
-        pub struct #client_name<P: ::combadge::Port + 'static> {
-            client: std::rc::Rc<std::cell::RefCell<::combadge::Client::<P>>>,
+    /// Types are checked once here rather than in each generated method so that each problem is
+    /// reported once, except for types involving generic parameters, which can only be checked in
+    /// the methods once the parameters are known.
+    fn postable(&self) -> TokenStream2 {
+        let postable = self
+            .methods
+            .sent_type
+            .iter()
+            .zip(&self.methods.method_parts)
+            .flat_map(|(sent_type, method_parts)| {
+                let generic = self.generic_arguments(method_parts);
+                sent_type
+                    .iter()
+                    .filter(move |ty| !mentions((*ty).clone(), &generic))
+                    .map(assert_postable)
+            })
+            .collect::<Vec<_>>();
+        quote! {
+            const _: () = {
+                #(#postable)*
+            };
         }
+    }
 
-        impl<P: ::combadge::Port + 'static> #client_name<P> {
-            pub fn new(port: P) -> Self {
-                Self { client: ::combadge::Client::new(port) }
-            }
+    fn client_postable(&self) -> Vec<TokenStream2> {
+        self.generic_sent_type()
+            .iter()
+            .map(|generic_sent_type| {
+                if generic_sent_type.is_empty() {
+                    return quote! {};
+                }
+                let assertion = generic_sent_type.iter().map(assert_postable);
+                quote! { const { #(#assertion)* }; }
+            })
+            .collect()
+    }
 
-            #(
-                #[expect(clippy::future_not_send)]
-                pub fn #name(#(#argument),*) -> impl std::future::Future<Output = Result<#internal_type, ::combadge::Error>> {
-                    use ::combadge::reexports::futures::future::FutureExt;
-                    use ::combadge::reexports::futures::future::TryFutureExt;
-                    const _: () = assert!(<#internal_type as ::combadge::Post>::POSTABLE);
+    /// Generic methods on the client keep their own parameters, with `Post` bounds for the ones
+    /// sent.
+    fn method_generics(&self) -> Vec<TokenStream2> {
+        self.methods
+            .functions
+            .iter()
+            .zip(&self.methods.method_parts)
+            .map(|(function, method_parts)| {
+                let lifetime = function.sig.generics.lifetimes();
+                let declaration = &method_parts.declaration;
+                quote! { #(#lifetime,)* #(#declaration),* }
+            })
+            .collect()
+    }
 
-                    let message = Ok(::combadge::Message::new(#name_string));
-                    #(
-                        const _: () = assert!(<#non_receiver_type as ::combadge::Post>::POSTABLE);
-                        let message = message.and_then(|mut message| {
-                            message.post(#non_receiver_name)?;
-                            Ok(message)
-                        });
-                    )*
+    /// Generic types that are borrowed need to be `ToOwned` to be sent.
+    fn borrowed_predicate(&self) -> Vec<Vec<TokenStream2>> {
+        self.methods
+            .borrowed
+            .iter()
+            .zip(&self.methods.method_parts)
+            .map(|(borrowed, method_parts)| {
+                let generic = self.generic_arguments(method_parts);
+                borrowed
+                    .iter()
+                    .flatten()
+                    .filter(|referent| mentions(referent.to_token_stream(), &generic))
+                    .map(|referent| quote! { #referent: ::std::borrow::ToOwned })
+                    .collect()
+            })
+            .collect()
+    }
 
-                    let server_ready = match self
-                        .client
-                        .try_borrow_mut()
-                        .map_err(|_| ::combadge::Error::ClientUnavailable)
-                    {
-                        Ok(mut client) => client.wait_for_server().map(|()| Ok(())).left_future(),
-                        Err(error) => async { Err(error) }.right_future(),
-                    };
+    fn method_predicate(&self) -> Vec<Vec<TokenStream2>> {
+        self.methods
+            .method_parts
+            .iter()
+            .zip(&self.methods.sent_type)
+            .zip(self.borrowed_predicate())
+            .map(|((method_parts, sent_type), borrowed_predicate)| {
+                method_parts
+                    .predicate
+                    .iter()
+                    .cloned()
+                    .chain(method_parts.post_predicates(sent_type))
+                    .chain(borrowed_predicate)
+                    .collect()
+            })
+            .collect()
+    }
 
-                    let client_clone = self.client.clone();
-                    server_ready.then(move |result| {
-                        let message = result.and(message);
-                        async { message }.and_then(move |message| {
-                            let client = client_clone
-                                .try_borrow_mut()
-                                .map_err(|_| ::combadge::Error::ClientUnavailable);
-                            let message = client.map(|mut client| client.send_message::<#internal_type>(message));
-                            async { message }.try_flatten().map(|result| {
-                                let result: Result<#internal_type, ::combadge::Error> = result.map(std::convert::Into::into);
-                                result
-                            })
-                        })
-                    })
-                }
-            )*
+    /// Builds the message calling each method from the client's arguments.
+    fn build_message(&self) -> Vec<TokenStream2> {
+        let Methods {
+            id,
+            name_string,
+            borrowed,
+            non_receiver_name,
+            ..
+        } = &self.methods;
+        let client_value = converted(borrowed, non_receiver_name, |name| {
+            quote! { ::std::borrow::ToOwned::to_owned(#name) }
+        });
+        id.iter()
+            .zip(name_string)
+            .zip(&client_value)
+            .map(|((id, name_string), client_value)| {
+                quote! {
+                    {
+                        let message = Ok(::combadge::Message::new_procedure(&self.route, #id, #name_string));
+                        #(
+                            let message = message.and_then(|mut message| {
+                                message.post(#client_value)?;
+                                Ok(message)
+                            });
+                        )*
+                        message
+                    }
+                }
+            })
+            .collect()
+    }
+
+    fn client(&self) -> TokenStream2 {
+        let Self {
+            parts,
+            client_name,
+            batch_name,
+            ..
+        } = self;
+        let GenericParts {
+            declaration: trait_declaration,
+            argument: trait_argument,
+            type_param: trait_type_param,
+            ..
+        } = parts;
+        let supertrait_field = self.supertrait_fields();
+        let supertrait_client = self.supertraits.iter().map(|supertrait| &supertrait.client);
+
+        // Supertraits' methods can be called on the client and the batch builder through their
+        // routes, which pass them on to the supertraits' own. Only direct supertraits are routed, so
+        // a trait lists its supertraits' supertraits too, which their routes check
+        let supertrait_route = self
+            .supertraits
+            .iter()
+            .zip(&supertrait_field)
+            .map(|(supertrait, field)| {
+                let Supertrait {
+                    route,
+                    client,
+                    batch,
+                    ..
+                } = supertrait;
+                // Unlisted supertraits' supertraits are reported on the supertrait they're missing from
+                quote_spanned! {route.span()=>
+                    impl<P: ::combadge::Port + 'static, #(#trait_declaration),*> #route for #client_name<P, #(#trait_argument),*> {
+                        type Target = #client;
+
+                        fn route(&self) -> &#client {
+                            &self.#field
+                        }
+
+                        fn route_mut(&mut self) -> &mut #client {
+                            &mut self.#field
+                        }
+                    }
+
+                    impl<P: ::combadge::Port + 'static, #(#trait_declaration),*> #route for #batch_name<P, #(#trait_argument),*> {
+                        type Target = #batch;
+
+                        fn route(&self) -> &#batch {
+                            &self.#field
+                        }
+
+                        fn route_mut(&mut self) -> &mut #batch {
+                            &mut self.#field
+                        }
+                    }
+                }
+            });
+
+        quote! {
+            pub struct #client_name<P: ::combadge::Port + 'static, #(#trait_declaration),*> {
+                client: std::rc::Rc<std::cell::RefCell<::combadge::Client::<P>>>,
+                route: std::rc::Rc<[u32]>,
+                #(
+                    #supertrait_field: #supertrait_client,
+                )*
+                phantom_: std::marker::PhantomData<fn() -> (#(#trait_type_param,)*)>,
+            }
+
+            impl<P: ::combadge::Port + 'static, #(#trait_declaration),*> Clone for #client_name<P, #(#trait_argument),*> {
+                fn clone(&self) -> Self {
+                    Self {
+                        client: self.client.clone(),
+                        route: self.route.clone(),
+                        #(
+                            #supertrait_field: Clone::clone(&self.#supertrait_field),
+                        )*
+                        phantom_: std::marker::PhantomData,
+                    }
+                }
+            }
+
+            #(#supertrait_route)*
+
+            impl<P: ::combadge::Port + std::fmt::Debug + 'static, #(#trait_declaration),*> std::fmt::Debug for #client_name<P, #(#trait_argument),*> {
+                fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                    formatter
+                        .debug_struct(stringify!(#client_name))
+                        .field("client", &self.client)
+                        .finish()
+                }
+            }
         }
-    };
+    }
+
+    fn client_constructors(&self) -> TokenStream2 {
+        let Self {
+            item,
+            parts,
+            client_predicate,
+            client_name,
+            ..
+        } = self;
+        let GenericParts {
+            declaration: trait_declaration,
+            argument: trait_argument,
+            ..
+        } = parts;
+        let signature = self.signature();
+        let client_signature =
+            self.supertraits
+                .iter()
+                .fold(quote! { #signature }, |signature, supertrait| {
+                    let client = &supertrait.client;
+                    quote! { ::combadge::extend_signature(#signature, <#client>::SIGNATURE) }
+                });
+        let instantiated_signature = self.instantiated_signature(|supertrait| &supertrait.client);
+        let supertrait_field = self.supertrait_fields();
+        let supertrait_index = self.supertrait_indices();
+        let supertrait_client = self
+            .supertraits
+            .iter()
+            .map(|supertrait| &supertrait.client)
+            .collect::<Vec<_>>();
+        let supertrait_accessor = self
+            .supertraits
+            .iter()
+            .map(|supertrait| format_ident!("as_{}", snake_case(&supertrait.trait_name)))
+            .collect::<Vec<_>>();
+        let supertrait_accessor_mut = supertrait_accessor
+            .iter()
+            .map(|accessor| format_ident!("{}_mut", accessor));
+
+        // Associated consts with a default are readable on the client, though an implementation on
+        // the server may override them
+        let client_const = item.items.iter().filter_map(|item| match item {
+            TraitItem::Const(constant) => constant.default.as_ref().map(|(_, value)| {
+                let doc = constant
+                    .attrs
+                    .iter()
+                    .filter(|attribute| attribute.path().is_ident("doc"));
+                let ident = &constant.ident;
+                let ty = &constant.ty;
+                quote! {
+                    #(#doc)*
+                    pub const #ident: #ty = #value;
+                }
+            }),
+            _ => None,
+        });
+
+        quote! {
+            impl<P: ::combadge::Port + 'static, #(#trait_declaration),*> #client_name<P, #(#trait_argument),*>
+            where
+                #(#client_predicate,)*
+            {
+                pub const SIGNATURE: u64 = #client_signature;
+
+                #(#client_const)*
+
+                /// The signature checked in the handshake, which tells apart instantiations of
+                /// generic traits.
+                pub fn signature() -> u64 {
+                    #instantiated_signature
+                }
+
+                pub fn new(port: P) -> Self {
+                    Self::with_route(::combadge::Client::new(port, Self::signature()), std::rc::Rc::from([]))
+                }
+
+                /// Creates a client that owns the value served on `port`, so that the server releases
+                /// the value once every clone of the client is dropped.
+                pub fn new_owning(port: P) -> Self {
+                    let client = ::combadge::Client::new(port, Self::signature());
+                    client.borrow_mut().release_on_drop();
+                    Self::with_route(client, std::rc::Rc::from([]))
+                }
+
+                /// Makes calls through a client created for a trait extending this one, prefixing them
+                /// with `route` so that its server passes them on to this trait's dispatcher.
+                pub fn with_route(client: std::rc::Rc<std::cell::RefCell<::combadge::Client::<P>>>, route: std::rc::Rc<[u32]>) -> Self {
+                    Self {
+                        #(
+                            #supertrait_field: <#supertrait_client>::with_route(
+                                client.clone(),
+                                route.iter().copied().chain([#supertrait_index]).collect(),
+                            ),
+                        )*
+                        client,
+                        route,
+                        phantom_: std::marker::PhantomData,
+                    }
+                }
+
+                #(
+                    pub fn #supertrait_accessor(&self) -> &#supertrait_client {
+                        &self.#supertrait_field
+                    }
+
+                    pub fn #supertrait_accessor_mut(&mut self) -> &mut #supertrait_client {
+                        &mut self.#supertrait_field
+                    }
+                )*
 
-    let server_name = format_ident!("{}Server", item.ident);
-    let server = quote! {
-        pub struct #server_name<P: ::combadge::Port + 'static> {
-            server: std::rc::Rc<std::cell::RefCell<::combadge::Server<P>>>,
+                /// Coalesces calls made within the same microtask into a single message.
+                pub fn set_batching(&self, batching: bool) -> Result<(), ::combadge::Error> {
+                    self.client
+                        .try_borrow_mut()
+                        .map_err(|_| ::combadge::Error::ClientUnavailable)?
+                        .set_batching(batching);
+                    Ok(())
+                }
+            }
         }
+    }
+
+    fn client_calls(&self) -> TokenStream2 {
+        let Self {
+            parts,
+            client_predicate,
+            client_name,
+            ..
+        } = self;
+        let GenericParts {
+            declaration: trait_declaration,
+            argument: trait_argument,
+            ..
+        } = parts;
+        let Methods {
+            name,
+            client_input,
+            internal_type,
+            ..
+        } = &self.methods;
+        let method_generics = self.method_generics();
+        let method_predicate = self.method_predicate();
+        let client_postable = self.client_postable();
+        let build_message = self.build_message();
+
+        // Methods returning a Handle also get a variant that returns the handle's client immediately
+        let (pipelined_index, pipelined_target): (Vec<_>, Vec<_>) = internal_type
+            .iter()
+            .enumerate()
+            .filter_map(|(index, internal_type)| {
+                let internal_type = syn::parse2::<Type>(internal_type.clone()).ok()?;
+                handle_target(&internal_type).map(|target| (index, target.clone()))
+            })
+            .unzip();
+        let pipelined_name = pipelined_index
+            .iter()
+            .map(|index| format_ident!("{}_pipelined", name[*index]));
+        let pipelined_input = pipelined_index.iter().map(|index| &client_input[*index]);
+        let pipelined_message = pipelined_index.iter().map(|index| &build_message[*index]);
+        let pipelined_generics = pipelined_index.iter().map(|index| &method_generics[*index]);
+        let pipelined_predicate = pipelined_index
+            .iter()
+            .map(|index| &method_predicate[*index]);
+
+        quote! {
+            impl<P: ::combadge::Port + 'static, #(#trait_declaration),*> #client_name<P, #(#trait_argument),*>
+            where
+                #(#client_predicate,)*
+            {
+                #(
+                    #[expect(clippy::future_not_send)]
+                    pub fn #name<#method_generics>(#client_input) -> impl std::future::Future<Output = Result<#internal_type, ::combadge::Error>>
+                    where
+                        #(#method_predicate,)*
+                    {
+                        #client_postable
+                        let message = #build_message;
+                        ::combadge::Client::call::<#internal_type>(&self.client, message)
+                    }
+                )*
+
+                #(
+                    /// Returns a client for the resulting handle without waiting for the call to
+                    /// complete. Calls made on it are queued until the server has produced the result.
+                    pub fn #pipelined_name<#pipelined_generics>(#pipelined_input) -> Result<<#pipelined_target as ::combadge::AsHandle<#pipelined_target>>::Client, ::combadge::Error>
+                    where
+                        #(#pipelined_predicate,)*
+                    {
+                        let message = #pipelined_message;
+                        ::combadge::Client::pipeline::<#pipelined_target>(&self.client, message)
+                    }
+                )*
+            }
+        }
+    }
 
-        impl<P: ::combadge::Port + 'static> #server_name<P> {
-            pub fn create<L: #trait_name + 'static>(mut local: L, port: P) {
-                let dispatch = Box::new(move |procedure: &str, data| {
-                    match procedure {
+    /// Copies of the trait's local methods, rewritten to call the client.
+    fn client_local(&self) -> TokenStream2 {
+        let Self {
+            parts,
+            client_predicate,
+            client_name,
+            ..
+        } = self;
+        let GenericParts {
+            declaration: trait_declaration,
+            argument: trait_argument,
+            ..
+        } = parts;
+        let client_method = self
+            .methods
+            .name
+            .iter()
+            .chain(self.local.name.iter().copied())
+            .cloned()
+            .collect::<Vec<_>>();
+        let client_local = self.local.functions.iter().filter_map(|function| {
+            let mut body = function.default.clone()?;
+            ClientBody {
+                trait_name: &self.item.ident,
+                methods: &client_method,
+            }
+            .visit_block_mut(&mut body);
+
+            let doc = function
+                .attrs
+                .iter()
+                .filter(|attribute| attribute.path().is_ident("doc"));
+            let Signature {
+                ident,
+                generics,
+                inputs,
+                output,
+                ..
+            } = &function.sig;
+            let where_clause = &generics.where_clause;
+            let output = match output {
+                ReturnType::Default => quote! { () },
+                ReturnType::Type(_, ty) => quote! { #ty },
+            };
+            Some(quote! {
+                #(#doc)*
+                #[expect(clippy::future_not_send)]
+                pub async fn #ident #generics(#inputs) -> Result<#output, ::combadge::Error> #where_clause {
+                    Ok(#body)
+                }
+            })
+        });
+
+        quote! {
+            impl<P: ::combadge::Port + 'static, #(#trait_declaration),*> #client_name<P, #(#trait_argument),*>
+            where
+                #(#client_predicate,)*
+            {
+                #(#client_local)*
+            }
+        }
+    }
+
+    /// The batch builder, along with the client's method that starts a batch.
+    fn batch(&self) -> TokenStream2 {
+        let Self {
+            parts,
+            client_predicate,
+            client_name,
+            batch_name,
+            ..
+        } = self;
+        let GenericParts {
+            declaration: trait_declaration,
+            argument: trait_argument,
+            type_param: trait_type_param,
+            ..
+        } = parts;
+        let Methods {
+            name,
+            non_receiver_name,
+            non_receiver_type,
+            internal_type,
+            ..
+        } = &self.methods;
+        let method_generics = self.method_generics();
+        let method_predicate = self.method_predicate();
+        let client_postable = self.client_postable();
+        let build_message = self.build_message();
+        let supertrait_field = self.supertrait_fields();
+        let supertrait_index = self.supertrait_indices();
+        let supertrait_batch = self
+            .supertraits
+            .iter()
+            .map(|supertrait| &supertrait.batch)
+            .collect::<Vec<_>>();
+
+        quote! {
+            impl<P: ::combadge::Port + 'static, #(#trait_declaration),*> #client_name<P, #(#trait_argument),*>
+            where
+                #(#client_predicate,)*
+            {
+                /// Sends every call made on the builder in a single message, returning whatever `build`
+                /// returns (typically the calls' futures).
+                pub fn batch<R>(&self, build: impl FnOnce(&mut #batch_name<P, #(#trait_argument),*>) -> R) -> Result<R, ::combadge::Error> {
+                    let started = self
+                        .client
+                        .try_borrow_mut()
+                        .map_err(|_| ::combadge::Error::ClientUnavailable)?
+                        .begin_batch();
+
+                    let mut batch = #batch_name::with_route(self.client.clone(), self.route.clone());
+                    let result = build(&mut batch);
+
+                    if started {
+                        self.client
+                            .try_borrow_mut()
+                            .map_err(|_| ::combadge::Error::ClientUnavailable)?
+                            .end_batch(batch.stop_on_error);
+                    }
+
+                    Ok(result)
+                }
+            }
+
+            pub struct #batch_name<P: ::combadge::Port + 'static, #(#trait_declaration),*> {
+                client: std::rc::Rc<std::cell::RefCell<::combadge::Client::<P>>>,
+                route: std::rc::Rc<[u32]>,
+                stop_on_error: bool,
+                #(
+                    #supertrait_field: #supertrait_batch,
+                )*
+                phantom_: std::marker::PhantomData<fn() -> (#(#trait_type_param,)*)>,
+            }
+
+            impl<P: ::combadge::Port + 'static, #(#trait_declaration),*> #batch_name<P, #(#trait_argument),*>
+            where
+                #(#client_predicate,)*
+            {
+                /// Queues calls in a batch started on a client for a trait extending this one, prefixing
+                /// them with `route` as the client's `with_route` does.
+                pub fn with_route(client: std::rc::Rc<std::cell::RefCell<::combadge::Client::<P>>>, route: std::rc::Rc<[u32]>) -> Self {
+                    Self {
                         #(
-                            #name_string => Self::#name(&mut local, data),
+                            #supertrait_field: <#supertrait_batch>::with_route(
+                                client.clone(),
+                                route.iter().copied().chain([#supertrait_index]).collect(),
+                            ),
                         )*
-                        _ => Err(::combadge::Error::UnknownProcedure{ name: String::from(procedure) })
+                        client,
+                        route,
+                        stop_on_error: false,
+                        phantom_: std::marker::PhantomData,
                     }
-                });
+                }
+
+                /// Skips the rest of the batch on the server once a call fails to dispatch, for example
+                /// because its arguments fail to deserialize, and fails the skipped calls. Calls whose
+                /// methods return an error don't stop the batch.
+                pub fn stop_on_error(&mut self) {
+                    self.stop_on_error = true;
+                }
 
-                ::combadge::Server::create(port, dispatch);
+                #(
+                    #[expect(clippy::future_not_send)]
+                    pub fn #name<#method_generics>(&mut self, #(#non_receiver_name: #non_receiver_type),*) -> impl std::future::Future<Output = Result<#internal_type, ::combadge::Error>>
+                    where
+                        #(#method_predicate,)*
+                    {
+                        #client_postable
+                        let message = #build_message;
+                        ::combadge::Client::queue::<#internal_type>(&self.client, message)
+                    }
+                )*
             }
+        }
+    }
 
-            #(
-                fn #name(local_: &mut dyn #trait_name, data_: ::combadge::reexports::js_sys::Array) -> Result<(), ::combadge::Error> {
-                    use ::combadge::reexports::wasm_bindgen_futures::spawn_local;
+    /// Calls each method on a local value wrapped in `Local`, turning its result into a future.
+    fn adapter_call(&self) -> Vec<TokenStream2> {
+        let Methods {
+            name,
+            return_kind,
+            non_receiver_name,
+            ..
+        } = &self.methods;
+        return_kind
+            .iter()
+            .zip(name.iter().zip(self.method_turbofish()))
+            .zip(non_receiver_name)
+            .map(|((return_kind, (name, turbofish)), non_receiver_name)| {
+                let t = return_kind.output();
+                let call = return_kind.adapt(
+                    quote! { self.0.#name #turbofish(#(#non_receiver_name),*) },
+                    &t,
+                );
+                if return_kind.is_borrowing() {
+                    quote! { async move { Ok(#call.await) } }
+                } else {
+                    quote! {
+                        let result = ::combadge::MaybeAsync::<#t>::to_maybe_async(#call);
+                        async move { Ok(Box::into_pin(result).await) }
+                    }
+                }
+            })
+            .collect()
+    }
 
+    fn method_turbofish(&self) -> Vec<TokenStream2> {
+        self.methods
+            .method_parts
+            .iter()
+            .map(|method_parts| turbofish(&method_parts.argument))
+            .collect()
+    }
+
+    /// The async mirror lets code be written once against either the client or a local value.
+    fn mirror(&self) -> TokenStream2 {
+        let Self {
+            parts,
+            client_predicate,
+            client_name,
+            async_name,
+            ..
+        } = self;
+        let GenericParts {
+            declaration: trait_declaration,
+            argument: trait_argument,
+            ..
+        } = parts;
+        let Methods {
+            name,
+            client_input,
+            internal_type,
+            non_receiver_name,
+            ..
+        } = &self.methods;
+        let LocalMethods {
+            name: local_name,
+            generics: local_generics,
+            where_clause: local_where,
+            input: local_input,
+            output: local_output,
+            turbofish: local_turbofish,
+            argument: local_argument,
+            ..
+        } = &self.local;
+        let method_generics = self.method_generics();
+        let method_predicate = self.method_predicate();
+        let method_turbofish = self.method_turbofish();
+
+        // The mirror extends its supertraits' mirrors, which the client reaches through its routes
+        let supertrait_mirror = self
+            .supertraits
+            .iter()
+            .map(|supertrait| &supertrait.mirror)
+            .collect::<Vec<_>>();
+        let mirror_supertraits = if supertrait_mirror.is_empty() {
+            quote! {}
+        } else {
+            quote! { : #(#supertrait_mirror)+* }
+        };
+
+        quote! {
+            /// Mirrors the trait with every method returning a future of its result, so that code can be
+            /// written once for the client and for a local value wrapped in [`::combadge::Local`].
+            pub trait #async_name<#(#trait_declaration),*> #mirror_supertraits
+            where
+                #(#client_predicate,)*
+            {
+                #(
+                    fn #name<#method_generics>(#client_input) -> impl std::future::Future<Output = Result<#internal_type, ::combadge::Error>>
+                    where
+                        #(#method_predicate,)*;
+                )*
+
+                #(
+                    fn #local_name #local_generics(#local_input) -> impl std::future::Future<Output = Result<#local_output, ::combadge::Error>>
+                    #local_where;
+                )*
+            }
+
+            impl<P: ::combadge::Port + 'static, #(#trait_declaration),*> #async_name<#(#trait_argument),*> for #client_name<P, #(#trait_argument),*>
+            where
+                #(Self: #supertrait_mirror,)*
+                #(#client_predicate,)*
+            {
+                #(
+                    #[expect(clippy::future_not_send)]
+                    fn #name<#method_generics>(#client_input) -> impl std::future::Future<Output = Result<#internal_type, ::combadge::Error>>
+                    where
+                        #(#method_predicate,)*
+                    {
+                        Self::#name #method_turbofish(self, #(#non_receiver_name),*)
+                    }
+                )*
+
+                #(
+                    #[expect(clippy::future_not_send)]
+                    fn #local_name #local_generics(#local_input) -> impl std::future::Future<Output = Result<#local_output, ::combadge::Error>>
+                    #local_where
+                    {
+                        Self::#local_name #local_turbofish(self, #(#local_argument),*)
+                    }
+                )*
+            }
+        }
+    }
+
+    /// Implements the mirror for local values wrapped in `Local`, so that they can stand in for
+    /// the client.
+    fn local_mirror(&self) -> TokenStream2 {
+        let Self {
+            parts,
+            path: trait_path,
+            client_predicate,
+            async_name,
+            ..
+        } = self;
+        let GenericParts {
+            declaration: trait_declaration,
+            argument: trait_argument,
+            ..
+        } = parts;
+        let Methods {
+            name,
+            client_input,
+            internal_type,
+            ..
+        } = &self.methods;
+        let LocalMethods {
+            name: local_name,
+            generics: local_generics,
+            where_clause: local_where,
+            input: local_input,
+            output: local_output,
+            ..
+        } = &self.local;
+        let method_generics = self.method_generics();
+        let method_predicate = self.method_predicate();
+        let adapter_call = self.adapter_call();
+        let local_call = self.local.call();
+        let supertrait_mirror = self.supertraits.iter().map(|supertrait| &supertrait.mirror);
+
+        quote! {
+            // Whether these futures can be sent depends on the local value
+            #[allow(clippy::future_not_send)]
+            impl<L: #trait_path, #(#trait_declaration),*> #async_name<#(#trait_argument),*> for ::combadge::Local<L>
+            where
+                #(Self: #supertrait_mirror,)*
+                #(#client_predicate,)*
+            {
+                #(
+                    fn #name<#method_generics>(#client_input) -> impl std::future::Future<Output = Result<#internal_type, ::combadge::Error>>
+                    where
+                        #(#method_predicate,)*
+                    {
+                        #adapter_call
+                    }
+                )*
+
+                #(
+                    fn #local_name #local_generics(#local_input) -> impl std::future::Future<Output = Result<#local_output, ::combadge::Error>>
+                    #local_where
+                    {
+                        #local_call
+                    }
+                )*
+            }
+        }
+    }
+
+    /// The route trait, which lets clients for traits extending this one pass its methods on to
+    /// the supertrait's client they hold.
+    fn route_trait(&self) -> TokenStream2 {
+        let Self {
+            item, route_name, ..
+        } = self;
+
+        // Only direct supertraits are routed, so a route requires its supertraits' routes to make
+        // traits extending this one list them too
+        let supertrait_route = self
+            .supertraits
+            .iter()
+            .map(|supertrait| &supertrait.route)
+            .collect::<Vec<_>>();
+        let route_supertraits = if supertrait_route.is_empty() {
+            quote! {}
+        } else {
+            quote! { : #(#supertrait_route)+* }
+        };
+        let trait_name = &item.ident;
+        let unrouted_message = format!("`{{Self}}` doesn't route calls to `{trait_name}`");
+        let unrouted_note = format!(
+            "supertraits' supertraits aren't routed, so every remote trait extending `{trait_name}` has to list it among its supertraits"
+        );
+
+        quote! {
+            /// Lets the client and batch builder of a trait extending this one pass calls to this
+            /// trait's methods on to the client or batch builder they hold for it.
+            #[diagnostic::on_unimplemented(message = #unrouted_message, note = #unrouted_note)]
+            pub trait #route_name #route_supertraits {
+                type Target;
+
+                fn route(&self) -> &<Self as #route_name>::Target;
+                fn route_mut(&mut self) -> &mut <Self as #route_name>::Target;
+            }
+        }
+    }
+
+    /// Implements the mirror for anything routing to this trait's client or batch builder.
+    fn routing(&self) -> TokenStream2 {
+        let Self {
+            parts,
+            client_predicate,
+            async_name,
+            route_name,
+            ..
+        } = self;
+        let GenericParts {
+            declaration: trait_declaration,
+            argument: trait_argument,
+            ..
+        } = parts;
+        let Methods {
+            name,
+            shared,
+            client_input,
+            internal_type,
+            non_receiver_name,
+            ..
+        } = &self.methods;
+        let LocalMethods {
+            functions: local_functions,
+            name: local_name,
+            generics: local_generics,
+            where_clause: local_where,
+            input: local_input,
+            output: local_output,
+            turbofish: local_turbofish,
+            argument: local_argument,
+        } = &self.local;
+        let method_generics = self.method_generics();
+        let method_predicate = self.method_predicate();
+        let supertrait_mirror = self.supertraits.iter().map(|supertrait| &supertrait.mirror);
+
+        let async_path = quote! { #async_name<#(#trait_argument),*> };
+        let routed_target = quote! { <Routed_ as #route_name>::Target };
+        let routed = |name: &Ident, turbofish: &TokenStream2, shared: bool, argument: &[Ident]| {
+            let route = if shared {
+                quote! { #route_name::route(self) }
+            } else {
+                quote! { #route_name::route_mut(self) }
+            };
+            quote! { <#routed_target as #async_path>::#name #turbofish(#route, #(#argument),*) }
+        };
+        let method_turbofish = self.method_turbofish();
+        let routed_call = name
+            .iter()
+            .zip(shared)
+            .zip(method_turbofish.iter().zip(non_receiver_name))
+            .map(|((name, shared), (turbofish, non_receiver_name))| {
+                routed(name, turbofish, *shared, non_receiver_name)
+            });
+        let routed_local_call = local_functions
+            .iter()
+            .zip(local_name.iter().zip(local_turbofish))
+            .zip(local_argument)
+            .map(|((function, (name, turbofish)), argument)| {
+                let shared = function
+                    .sig
+                    .receiver()
+                    .is_some_and(|receiver| receiver.mutability.is_none());
+                routed(name, turbofish, shared, argument)
+            });
+
+        let route_trait = self.route_trait();
+
+        quote! {
+            #route_trait
+
+            // Whether these futures can be sent depends on the target
+            #[allow(clippy::future_not_send)]
+            impl<Routed_: #route_name, #(#trait_declaration),*> #async_path for Routed_
+            where
+                #routed_target: #async_path,
+                #(Self: #supertrait_mirror,)*
+                #(#client_predicate,)*
+            {
+                #(
+                    fn #name<#method_generics>(#client_input) -> impl std::future::Future<Output = Result<#internal_type, ::combadge::Error>>
+                    where
+                        #(#method_predicate,)*
+                    {
+                        #routed_call
+                    }
+                )*
+
+                #(
+                    fn #local_name #local_generics(#local_input) -> impl std::future::Future<Output = Result<#local_output, ::combadge::Error>>
+                    #local_where
+                    {
+                        #routed_local_call
+                    }
+                )*
+            }
+        }
+    }
+
+    /// The batch builder's methods as a trait, which builders for traits extending this one get
+    /// through their routes.
+    fn batched(&self) -> TokenStream2 {
+        let Self {
+            parts,
+            client_predicate,
+            batch_name,
+            route_name,
+            batched_name,
+            ..
+        } = self;
+        let GenericParts {
+            declaration: trait_declaration,
+            argument: trait_argument,
+            ..
+        } = parts;
+        let Methods {
+            name,
+            non_receiver_name,
+            non_receiver_type,
+            internal_type,
+            method_parts,
+            ..
+        } = &self.methods;
+        let method_generics = self.method_generics();
+        let method_predicate = self.method_predicate();
+        let method_turbofish = self.method_turbofish();
+
+        let batched_path = quote! { #batched_name<#(#trait_argument),*> };
+        let routed_target = quote! { <Routed_ as #route_name>::Target };
+        let routed_batch_call = name
+            .iter()
+            .zip(method_turbofish.iter().zip(non_receiver_name))
+            .map(|(name, (turbofish, non_receiver_name))| {
+                quote! { <#routed_target as #batched_path>::#name #turbofish(#route_name::route_mut(self), #(#non_receiver_name),*) }
+            });
+
+        // Unlike the builder's own methods, futures returned from a trait borrow the builder unless
+        // told otherwise, which would stop more than one call being queued
+        let batched_capture = |captor: TokenStream2| {
+            method_parts
+                .iter()
+                .map(|method_parts| {
+                    let argument = trait_argument.iter().chain(&method_parts.argument);
+                    quote! { + use<#captor, #(#argument),*> }
+                })
+                .collect::<Vec<_>>()
+        };
+        let batched_trait_capture = batched_capture(quote! { Self });
+        let batched_batch_capture = batched_capture(quote! { P });
+        let batched_routed_capture = batched_capture(quote! { Routed_ });
+
+        quote! {
+            /// Mirrors the trait's remote methods as calls queued on a batch builder, so that they can
+            /// be queued on the builder of a trait extending this one too.
+            pub trait #batched_name<#(#trait_declaration),*>
+            where
+                #(#client_predicate,)*
+            {
+                #(
+                    fn #name<#method_generics>(&mut self, #(#non_receiver_name: #non_receiver_type),*) -> impl std::future::Future<Output = Result<#internal_type, ::combadge::Error>> #batched_trait_capture
+                    where
+                        #(#method_predicate,)*;
+                )*
+            }
+
+            impl<P: ::combadge::Port + 'static, #(#trait_declaration),*> #batched_path for #batch_name<P, #(#trait_argument),*>
+            where
+                #(#client_predicate,)*
+            {
+                #(
+                    #[expect(clippy::future_not_send)]
+                    fn #name<#method_generics>(&mut self, #(#non_receiver_name: #non_receiver_type),*) -> impl std::future::Future<Output = Result<#internal_type, ::combadge::Error>> #batched_batch_capture
+                    where
+                        #(#method_predicate,)*
+                    {
+                        Self::#name #method_turbofish(self, #(#non_receiver_name),*)
+                    }
+                )*
+            }
+
+            // Whether these futures can be sent depends on the target
+            #[allow(clippy::future_not_send)]
+            impl<Routed_: #route_name, #(#trait_declaration),*> #batched_path for Routed_
+            where
+                #routed_target: #batched_path,
+                #(#client_predicate,)*
+            {
+                #(
+                    fn #name<#method_generics>(&mut self, #(#non_receiver_name: #non_receiver_type),*) -> impl std::future::Future<Output = Result<#internal_type, ::combadge::Error>> #batched_routed_capture
+                    where
+                        #(#method_predicate,)*
+                    {
+                        #routed_batch_call
+                    }
+                )*
+            }
+        }
+    }
+
+    /// The server's generic parameters, which are the trait's followed by its methods', along with
+    /// their bounds.
+    fn server_parts(&self) -> (GenericParts, Vec<TokenStream2>) {
+        let mut server_parts = GenericParts::of(&self.item.generics, &[]);
+        for (function, renames) in self
+            .methods
+            .functions
+            .iter()
+            .zip(&self.methods.method_renames)
+        {
+            server_parts.extend(GenericParts::of(&function.sig.generics, renames));
+        }
+
+        let server_sent_type = self
+            .methods
+            .sent_type
+            .iter()
+            .zip(&self.methods.method_renames)
+            .flat_map(|(sent_type, renames)| {
+                sent_type
+                    .iter()
+                    .map(|ty| rename_idents(ty.clone(), renames))
+            })
+            .collect::<Vec<_>>();
+        let server_predicate = server_parts
+            .predicate
+            .iter()
+            .cloned()
+            .chain(server_parts.post_predicates(&server_sent_type))
+            .chain(
+                self.borrowed_predicate()
+                    .iter()
+                    .zip(&self.methods.method_renames)
+                    .flat_map(|(borrowed_predicate, renames)| {
+                        borrowed_predicate
+                            .iter()
+                            .map(|predicate| rename_idents(predicate.clone(), renames))
+                    }),
+            )
+            .collect();
+        (server_parts, server_predicate)
+    }
+
+    fn server(&self) -> TokenStream2 {
+        let Self {
+            concurrency: trait_concurrency,
+            path: trait_path,
+            server_name,
+            ..
+        } = self;
+        let Methods {
+            name,
+            name_string,
+            id,
+            concurrency,
+            ..
+        } = &self.methods;
+        let (server_parts, server_predicate) = self.server_parts();
+        let GenericParts {
+            declaration: server_declaration,
+            argument: server_argument_type,
+            type_param: server_type_param,
+            ..
+        } = &server_parts;
+        let signature = self.signature();
+        let server_signature =
+            self.supertraits
+                .iter()
+                .fold(quote! { #signature }, |signature, supertrait| {
+                    let server = &supertrait.server;
+                    quote! { ::combadge::extend_signature(#signature, <#server>::SIGNATURE) }
+                });
+        let instantiated_signature = self.instantiated_signature(|supertrait| &supertrait.server);
+        let supertrait_field = self.supertrait_fields();
+        let supertrait_index = self.supertrait_indices();
+        let supertrait_server = self.supertraits.iter().map(|supertrait| &supertrait.server);
+
+        // Methods without their own policy share the trait's limiter
+        let limiter = name
+            .iter()
+            .map(|name| format_ident!("{}_limiter", name))
+            .collect::<Vec<_>>();
+        let create_limiter = concurrency.iter().map(|concurrency| {
+            concurrency.as_ref().map_or_else(
+                || quote! { trait_limiter.clone() },
+                |concurrency| quote! { #concurrency.limiter() },
+            )
+        });
+
+        quote! {
+            /// Serves the trait on a port. Besides the trait's own generic parameters, it takes those of
+            /// each generic method, prefixed with the method's name, to pick the one instantiation of
+            /// the method it serves.
+            pub struct #server_name<P: ::combadge::Port + 'static, #(#server_declaration),*> {
+                server: std::rc::Rc<std::cell::RefCell<::combadge::Server<P>>>,
+                phantom_: std::marker::PhantomData<fn() -> (#(#server_type_param,)*)>,
+            }
+
+            impl<P: ::combadge::Port + 'static, #(#server_declaration),*> #server_name<P, #(#server_argument_type),*>
+            where
+                #(#server_predicate,)*
+            {
+                pub const SIGNATURE: u64 = #server_signature;
+
+                /// The signature checked in the handshake, which tells apart instantiations of
+                /// generic traits.
+                pub fn signature() -> u64 {
+                    #instantiated_signature
+                }
+
+                pub fn create<L: #trait_path + 'static>(local: L, port: P) -> ::combadge::Revoker {
+                    Self::create_shared(std::rc::Rc::new(std::cell::RefCell::new(local)), port)
+                }
+
+                /// Serves a value that's also used elsewhere. Pass clones of the same `Guarded` to
+                /// serve it on several ports so that their calls queue together.
+                pub fn create_shared<L: #trait_path + ?Sized + 'static>(local: impl Into<::combadge::Guarded<L>>, port: P) -> ::combadge::Revoker {
+                    ::combadge::Server::create(port, Self::signature(), Box::new(Self::dispatcher(local.into())))
+                }
+
+                /// Returns the function that dispatches calls to `local`. Servers of traits extending
+                /// this one pass calls to this trait's methods on to it.
+                pub fn dispatcher<L: #trait_path + ?Sized + 'static>(
+                    local: ::combadge::Guarded<L>,
+                ) -> impl FnMut(&::combadge::Procedure, ::combadge::reexports::js_sys::Array) -> Result<(), ::combadge::Error> {
+                    let trait_limiter = #trait_concurrency.limiter();
                     #(
-                        const _: () = assert!(<#non_receiver_type as ::combadge::Post>::POSTABLE);
-                        let #non_receiver = ::combadge::Post::from_js_value(data_.shift())?;
+                        let #limiter = #create_limiter;
                     )*
-                    let result = local_.#name(#(#non_receiver_name),*);
-                    let port: ::combadge::reexports::web_sys::MessagePort = data_.shift().into();
-                    let async_result = ::combadge::MaybeAsync::to_maybe_async(result);
-                    let future_result = async move {
-                        let result: #internal_type = Box::into_pin(async_result).await;
-                        let value = match ::combadge::Post::to_js_value(result) {
-                            Ok(value) => value,
-                            Err(error) => {
-                                ::combadge::log_error!("error while converting to JsValue in future: {error:?}");
-                                return;
-                            }
+                    #(
+                        let mut #supertrait_field = <#supertrait_server>::dispatcher(local.clone());
+                    )*
+                    move |procedure: &::combadge::Procedure, data: ::combadge::reexports::js_sys::Array| {
+                        if let Some((index, procedure)) = procedure.supertrait(&data)? {
+                            return match index {
+                                #(
+                                    #supertrait_index => #supertrait_field(&procedure, data),
+                                )*
+                                _ => Err(::combadge::Error::UnknownProcedure{ name: format!("supertrait {index}") }),
+                            };
+                        }
+
+                        let id = match procedure {
+                            ::combadge::Procedure::Id(id) => *id,
+                            ::combadge::Procedure::Name(name) => match name.as_str() {
+                                #(
+                                    #name_string => #id,
+                                )*
+                                _ => return Err(::combadge::Error::UnknownProcedure{ name: name.clone() }),
+                            },
                         };
 
-                        if let Err(error) = <#internal_type as ::combadge::Transfer>::get_transferable(&value).map_or_else(
-                            || port.post_message(&value),
-                            |transferable| port.post_message_with_transferable(&value, &transferable))
-                        {
-                            ::combadge::log_error!("error while posting {value:?} {} in {} async: {error:?}", std::any::type_name::<#internal_type>(), #name_string);
+                        // IDs are dense unless assigned explicitly, so this compiles to a jump table
+                        match id {
+                            #(
+                                #id => Self::#name(&local, #limiter.as_ref(), data),
+                            )*
+                            _ => Err(::combadge::Error::UnknownProcedure{ name: procedure.to_string() })
                         }
-                    };
-                    spawn_local(future_result);
-                    Ok(())
+                    }
                 }
-            )*
+            }
         }
-    };
+    }
 
-    let result: TokenStream = quote! {
-        #item
-        #client
-        #server
+    /// The server names arguments itself so that they can't be shadowed by its own variables.
+    fn server_argument(&self) -> Vec<Vec<Ident>> {
+        self.methods
+            .non_receiver_type
+            .iter()
+            .map(|non_receiver_type| {
+                (0..non_receiver_type.len())
+                    .map(|index| format_ident!("argument{}_", index))
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn server_internal_type(&self) -> Vec<TokenStream2> {
+        self.methods
+            .internal_type
+            .iter()
+            .zip(&self.methods.method_renames)
+            .map(|(ty, renames)| rename_idents(ty.clone(), renames))
+            .collect()
+    }
+
+    /// Calls each method on the guarded local value, with the access its receiver needs.
+    fn invoke(&self) -> Vec<TokenStream2> {
+        let Methods {
+            name,
+            shared,
+            return_kind,
+            borrowed,
+            method_renames,
+            ..
+        } = &self.methods;
+        let server_value = converted(borrowed, &self.server_argument(), |name| {
+            quote! { ::std::borrow::Borrow::borrow(&#name) }
+        });
+        let server_turbofish = self
+            .method_turbofish()
+            .into_iter()
+            .zip(method_renames)
+            .map(|(turbofish, renames)| rename_idents(turbofish, renames));
+
+        return_kind
+            .iter()
+            .zip(shared)
+            .zip(name.iter().zip(&server_value))
+            .zip(server_turbofish.zip(self.server_internal_type()))
+            .map(|(((return_kind, shared), (name, server_value)), (turbofish, t))| {
+                let call = return_kind.adapt(quote! { local.#name #turbofish(#(#server_value),*) }, &t);
+                match (return_kind.is_borrowing(), shared) {
+                    (false, false) => quote! {
+                        local_.call(limiter_, move |local: &mut L| -> Box<dyn std::future::Future<Output = #t>> {
+                            ::combadge::MaybeAsync::<#t>::to_maybe_async(#call)
+                        })
+                    },
+                    (false, true) => quote! {
+                        local_.call_shared(limiter_, move |local: &L| -> Box<dyn std::future::Future<Output = #t>> {
+                            ::combadge::MaybeAsync::<#t>::to_maybe_async(#call)
+                        })
+                    },
+                    (true, false) => quote! {
+                        local_.call_borrowing(limiter_, move |local| Box::pin(async move { #call.await }))
+                    },
+                    (true, true) => quote! {
+                        local_.call_shared_borrowing(limiter_, move |local| Box::pin(async move { #call.await }))
+                    },
+                }
+            })
+            .collect()
+    }
+
+    /// The procedures the server's dispatcher calls, one per method, which read the arguments,
+    /// invoke the method and respond with its result.
+    fn server_procedures(&self) -> TokenStream2 {
+        let Self {
+            path: trait_path,
+            server_name,
+            ..
+        } = self;
+        let Methods {
+            name,
+            name_string,
+            wire_type,
+            method_renames,
+            ..
+        } = &self.methods;
+        let (server_parts, server_predicate) = self.server_parts();
+        let GenericParts {
+            declaration: server_declaration,
+            argument: server_argument_type,
+            ..
+        } = &server_parts;
+        let server_argument = self.server_argument();
+        let server_wire_type = wire_type
+            .iter()
+            .zip(method_renames)
+            .map(|(wire_type, renames)| {
+                wire_type
+                    .iter()
+                    .map(|ty| rename_idents(ty.clone(), renames))
+                    .collect::<Vec<_>>()
+            });
+        let server_internal_type = self.server_internal_type();
+        let server_postable = self
+            .generic_sent_type()
+            .into_iter()
+            .zip(method_renames)
+            .map(|(generic_sent_type, renames)| {
+                if generic_sent_type.is_empty() {
+                    return quote! {};
+                }
+                let assertion = generic_sent_type
+                    .iter()
+                    .map(|ty| assert_postable(&rename_idents(ty.clone(), renames)));
+                quote! { const { #(#assertion)* }; }
+            });
+        let invoke = self.invoke();
+
+        quote! {
+            impl<P: ::combadge::Port + 'static, #(#server_declaration),*> #server_name<P, #(#server_argument_type),*>
+            where
+                #(#server_predicate,)*
+            {
+                #(
+                    fn #name<L: #trait_path + ?Sized + 'static>(local_: &::combadge::Guarded<L>, limiter_: Option<&::combadge::Semaphore>, data_: ::combadge::reexports::js_sys::Array) -> Result<(), ::combadge::Error> {
+                        use ::combadge::reexports::wasm_bindgen_futures::spawn_local;
+
+                        #server_postable
+                        #(
+                            let #server_argument: #server_wire_type = ::combadge::Post::from_js_value(data_.shift())?;
+                        )*
+                        // Pipelined calls pass the port to serve the result on ahead of the response port
+                        let pipeline: Option<::combadge::reexports::web_sys::MessagePort> =
+                            (data_.length() > 1).then(|| data_.shift().into());
+                        let port: ::combadge::reexports::web_sys::MessagePort = data_.shift().into();
+                        let result = #invoke;
+                        let future_result = async move {
+                            let result: #server_internal_type = match result.await {
+                                Ok(result) => result,
+                                Err(error) => {
+                                    ::combadge::log_error!("error while calling {}: {error}", #name_string);
+                                    ::combadge::fail(&error, &port);
+                                    return;
+                                }
+                            };
+
+                            if let Err(error) = ::combadge::respond(result, &port, pipeline) {
+                                ::combadge::log_error!("error while responding to {}: {error}", #name_string);
+                                ::combadge::fail(&error, &port);
+                            }
+                        };
+                        spawn_local(future_result);
+                        Ok(())
+                    }
+                )*
+            }
+        }
+    }
+
+    /// Traits that only take `&self` can also be served from behind an `Rc`, as long as their
+    /// supertraits can.
+    fn shared_impl(&self) -> TokenStream2 {
+        let Methods {
+            functions,
+            name,
+            shared,
+            non_receiver_name,
+            ..
+        } = &self.methods;
+        if !shared.iter().all(|shared| *shared) {
+            return quote! {};
+        }
+
+        let Self {
+            item,
+            parts,
+            path: trait_path,
+            ..
+        } = self;
+        let trait_declaration = &parts.declaration;
+        let trait_predicate = &parts.predicate;
+        let supertrait_bound = item.supertraits.iter().filter(|bound| {
+            matches!(bound, TypeParamBound::Trait(bound) if matches!(bound.modifier, TraitBoundModifier::None))
+        });
+        let signature = functions
+            .iter()
+            .map(|function| with_argument_names(&function.sig));
+        let delegate = functions
+            .iter()
+            .zip(name.iter().zip(non_receiver_name))
+            .zip(self.method_turbofish())
+            .map(|((function, (name, non_receiver_name)), turbofish)| {
+                let call = quote! { (**self).#name #turbofish(#(#non_receiver_name),*) };
+                if function.sig.asyncness.is_some() {
+                    quote! { #call.await }
+                } else {
+                    call
+                }
+            });
+        // Associated items are the shared value's own, rather than the trait's defaults
+        let associated = item.items.iter().filter_map(|item| match item {
+            TraitItem::Const(constant) => {
+                let ident = &constant.ident;
+                let ty = &constant.ty;
+                Some(quote! { const #ident: #ty = <L as #trait_path>::#ident; })
+            }
+            TraitItem::Type(associated) => {
+                let ident = &associated.ident;
+                let (declaration, argument, predicate) = associated.generics.split_for_impl();
+                Some(quote! { type #ident #declaration = <L as #trait_path>::#ident #argument #predicate; })
+            }
+            _ => None,
+        });
+        quote! {
+            impl<L: #trait_path + ?Sized, #(#trait_declaration),*> #trait_path for std::rc::Rc<L>
+            where
+                #(#trait_predicate,)*
+                #(std::rc::Rc<L>: #supertrait_bound,)*
+            {
+                #(#associated)*
+
+                #(
+                    #signature {
+                        #delegate
+                    }
+                )*
+            }
+        }
+    }
+
+    /// Traits that can be made into objects can be handed out as `Handle<dyn Trait>`, so that the
+    /// remote doesn't need to know which implementation it's talking to.
+    fn dyn_handle(&self) -> TokenStream2 {
+        if !is_dyn_compatible(self.item) {
+            return quote! {};
+        }
+
+        let Self {
+            parts,
+            path: trait_path,
+            client_predicate,
+            client_name,
+            server_name,
+            ..
+        } = self;
+        let trait_declaration = &parts.declaration;
+        let trait_argument = &parts.argument;
+        let (_, server_predicate) = self.server_parts();
+        quote! {
+            impl<#(#trait_declaration),*> ::combadge::AsHandle<dyn #trait_path> for dyn #trait_path
+            where
+                #(#client_predicate,)*
+                #(#server_predicate,)*
+            {
+                type Client = #client_name<::combadge::reexports::web_sys::MessagePort, #(#trait_argument),*>;
+                type Server = #server_name<::combadge::reexports::web_sys::MessagePort, #(#trait_argument),*>;
+
+                fn into_client(port: ::combadge::reexports::web_sys::MessagePort) -> Self::Client {
+                    Self::Client::new_owning(port)
+                }
+
+                fn create_server(local: std::rc::Rc<std::cell::RefCell<Self>>, port: ::combadge::reexports::web_sys::MessagePort) -> ::combadge::Revoker {
+                    Self::Server::create_shared(local, port)
+                }
+            }
+        }
     }
-    .into();
+}
 
-    // println!("{}", prettyplease::unparse(&parse(result.clone()).unwrap()));
+/// Checks that the trait's and its methods' generic parameters can be carried onto the generated
+/// types.
+fn check_generics(item: &ItemTrait, functions: &[&TraitItemFn]) -> syn::Result<()> {
+    if let Some(lifetime) = item.generics.lifetimes().next() {
+        return Err(syn::Error::new_spanned(
+            lifetime,
+            "remote traits can't have lifetime parameters",
+        ));
+    }
 
-    result
+    // P and L name the port and the local value in generated code
+    let reserved = item
+        .generics
+        .params
+        .iter()
+        .chain(
+            functions
+                .iter()
+                .flat_map(|function| &function.sig.generics.params),
+        )
+        .find_map(|param| match param {
+            GenericParam::Type(param) if param.ident == "P" || param.ident == "L" => {
+                Some(&param.ident)
+            }
+            GenericParam::Const(param) if param.ident == "P" || param.ident == "L" => {
+                Some(&param.ident)
+            }
+            _ => None,
+        });
+    if let Some(ident) = reserved {
+        return Err(syn::Error::new_spanned(
+            ident,
+            format!(
+                "the generic parameter name {ident} is used by the generated client and server"
+            ),
+        ));
+    }
+    Ok(())
+}
+
+/// Spells out generic arguments for a call, or nothing if there are none.
+fn turbofish(argument: &[Ident]) -> TokenStream2 {
+    if argument.is_empty() {
+        quote! {}
+    } else {
+        quote! { ::<#(#argument),*> }
+    }
+}
+
+/// Converts each argument with `convert` if it's borrowed, leaving owned arguments as they are.
+fn converted(
+    borrowed: &[Vec<Option<Type>>],
+    names: &[Vec<Ident>],
+    convert: impl Fn(&Ident) -> TokenStream2,
+) -> Vec<Vec<TokenStream2>> {
+    borrowed
+        .iter()
+        .zip(names)
+        .map(|(borrowed, names)| {
+            borrowed
+                .iter()
+                .zip(names)
+                .map(|(referent, name)| {
+                    if referent.is_some() {
+                        convert(name)
+                    } else {
+                        quote! { #name }
+                    }
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// The remote methods of a `#[combadge]` trait, with each field listing something about every
+/// method in the order they're declared.
+struct Methods<'a> {
+    functions: Vec<&'a TraitItemFn>,
+    name: Vec<Ident>,
+    name_string: Vec<String>,
+    id: Vec<u32>,
+    concurrency: Vec<Option<TokenStream2>>,
+    shared: Vec<bool>,
+    non_receiver_name: Vec<Vec<Ident>>,
+    non_receiver_type: Vec<Vec<Type>>,
+    /// The method's inputs, with each argument bound to its name
+    client_input: Vec<Punctuated<FnArg, Token![,]>>,
+    /// What each borrowed argument refers to
+    borrowed: Vec<Vec<Option<Type>>>,
+    /// The owned type each argument is posted as
+    wire_type: Vec<Vec<TokenStream2>>,
+    return_kind: Vec<ReturnKind>,
+    internal_type: Vec<TokenStream2>,
+    /// Everything the method posts, its arguments and its result
+    sent_type: Vec<Vec<TokenStream2>>,
+    method_parts: Vec<GenericParts>,
+    /// The names the server gives the method's generic parameters
+    method_renames: Vec<Vec<(Ident, Ident)>>,
+}
+
+impl<'a> Methods<'a> {
+    fn of(functions: Vec<&'a TraitItemFn>, options: Vec<MethodOptions>) -> syn::Result<Self> {
+        let id = procedure_ids(&options)?;
+        let shared = receiver_sharing(&functions)?;
+        let name = functions
+            .iter()
+            .map(|function| function.sig.ident.clone())
+            .collect::<Vec<_>>();
+
+        let non_receiver_type = functions
+            .iter()
+            .map(|function| {
+                function
+                    .sig
+                    .inputs
+                    .iter()
+                    .filter_map(|arg| match arg {
+                        FnArg::Receiver(_) => None,
+                        FnArg::Typed(typed) => Some((*typed.ty).clone()),
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        // Borrowed arguments are copied into their owned type to be posted, and the server lends
+        // the owned value it receives to the implementation
+        let borrowed = non_receiver_type
+            .iter()
+            .map(|non_receiver_type| {
+                non_receiver_type
+                    .iter()
+                    .map(borrowed_referent)
+                    .collect::<syn::Result<Vec<_>>>()
+            })
+            .collect::<syn::Result<Vec<_>>>()?;
+        let wire_type = borrowed
+            .iter()
+            .zip(&non_receiver_type)
+            .map(|(borrowed, non_receiver_type)| {
+                borrowed
+                    .iter()
+                    .zip(non_receiver_type)
+                    .map(|(referent, ty)| {
+                        referent.as_ref().map_or_else(
+                            || quote! { #ty },
+                            |referent| quote! { <#referent as ::std::borrow::ToOwned>::Owned },
+                        )
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        let return_kind = functions
+            .iter()
+            .map(|function| ReturnKind::of(&function.sig))
+            .collect::<Vec<_>>();
+        let internal_type = return_kind
+            .iter()
+            .map(ReturnKind::output)
+            .collect::<Vec<_>>();
+        let sent_type = wire_type
+            .iter()
+            .zip(&internal_type)
+            .map(|(wire_type, internal_type)| {
+                wire_type
+                    .iter()
+                    .chain(std::iter::once(internal_type))
+                    .cloned()
+                    .collect()
+            })
+            .collect();
+
+        Ok(Self {
+            name_string: name.iter().map(ToString::to_string).collect(),
+            name,
+            id,
+            concurrency: options
+                .into_iter()
+                .map(|options| options.concurrency)
+                .collect(),
+            shared,
+            non_receiver_name: functions
+                .iter()
+                .map(|function| argument_names(&function.sig))
+                .collect(),
+            client_input: functions
+                .iter()
+                .map(|function| with_argument_names(&function.sig).inputs)
+                .collect(),
+            non_receiver_type,
+            borrowed,
+            wire_type,
+            return_kind,
+            internal_type,
+            sent_type,
+            method_parts: functions
+                .iter()
+                .map(|function| GenericParts::of(&function.sig.generics, &[]))
+                .collect(),
+            method_renames: method_renames(&functions),
+            functions,
+        })
+    }
+}
+
+/// Returns whether each method takes `&self`. `&self` methods can run alongside each other, `&mut
+/// self` methods need the value to themselves.
+fn receiver_sharing(functions: &[&TraitItemFn]) -> syn::Result<Vec<bool>> {
+    functions
+        .iter()
+        .map(|function| match function.sig.receiver() {
+            Some(receiver) if receiver.reference.is_some() => Ok(receiver.mutability.is_none()),
+            Some(receiver) => Err(syn::Error::new_spanned(
+                receiver,
+                "expected self to be taken by reference (&self or &mut self)",
+            )),
+            None => Err(syn::Error::new_spanned(
+                &function.sig,
+                format!(
+                    "expected {} to have a receiver (self parameter)",
+                    function.sig.ident
+                ),
+            )),
+        })
+        .collect()
+}
+
+/// The server can only dispatch to one instantiation of each generic method, so it takes the
+/// method's parameters as its own, prefixed with the method's name.
+fn method_renames(functions: &[&TraitItemFn]) -> Vec<Vec<(Ident, Ident)>> {
+    functions
+        .iter()
+        .map(|function| {
+            let name = &function.sig.ident;
+            let type_prefix = upper_camel(name);
+            let const_prefix = name.to_string().to_uppercase();
+            function
+                .sig
+                .generics
+                .params
+                .iter()
+                .filter_map(|param| match param {
+                    GenericParam::Type(param) => Some((
+                        param.ident.clone(),
+                        format_ident!("{}{}", type_prefix, param.ident),
+                    )),
+                    GenericParam::Const(param) => Some((
+                        param.ident.clone(),
+                        format_ident!("{}_{}", const_prefix, param.ident),
+                    )),
+                    GenericParam::Lifetime(_) => None,
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// The local methods of a `#[combadge]` trait, which run their default implementation wherever
+/// they're called.
+struct LocalMethods<'a> {
+    functions: Vec<&'a TraitItemFn>,
+    name: Vec<&'a Ident>,
+    generics: Vec<Generics>,
+    where_clause: Vec<Option<WhereClause>>,
+    /// The method's inputs, with each argument bound to its name
+    input: Vec<Punctuated<FnArg, Token![,]>>,
+    argument: Vec<Vec<Ident>>,
+    output: Vec<TokenStream2>,
+    turbofish: Vec<TokenStream2>,
+}
+
+impl<'a> LocalMethods<'a> {
+    fn of(functions: Vec<&'a TraitItemFn>) -> syn::Result<Self> {
+        if let Some(function) = functions
+            .iter()
+            .find(|function| function.sig.receiver().is_none())
+        {
+            return Err(syn::Error::new_spanned(
+                &function.sig,
+                format!(
+                    "expected {} to have a receiver (self parameter)",
+                    function.sig.ident
+                ),
+            ));
+        }
+
+        let signature = functions
+            .iter()
+            .map(|function| with_argument_names(&function.sig))
+            .collect::<Vec<_>>();
+        Ok(Self {
+            name: functions
+                .iter()
+                .map(|function| &function.sig.ident)
+                .collect(),
+            where_clause: signature
+                .iter()
+                .map(|signature| signature.generics.where_clause.clone())
+                .collect(),
+            argument: signature.iter().map(argument_names).collect(),
+            output: functions
+                .iter()
+                .map(|function| match &function.sig.output {
+                    ReturnType::Default => quote! { () },
+                    ReturnType::Type(_, ty) => quote! { #ty },
+                })
+                .collect(),
+            turbofish: signature
+                .iter()
+                .map(|signature| turbofish(&GenericParts::of(&signature.generics, &[]).argument))
+                .collect(),
+            generics: signature
+                .iter()
+                .map(|signature| signature.generics.clone())
+                .collect(),
+            input: signature
+                .into_iter()
+                .map(|signature| signature.inputs)
+                .collect(),
+            functions,
+        })
+    }
+
+    /// Calls each method on a local value wrapped in `Local`, turning its result into a future.
+    fn call(&self) -> Vec<TokenStream2> {
+        self.functions
+            .iter()
+            .zip(self.name.iter().zip(&self.turbofish))
+            .zip(&self.argument)
+            .map(|((function, (name, turbofish)), argument)| {
+                let call = quote! { self.0.#name #turbofish(#(#argument),*) };
+                if function.sig.asyncness.is_some() {
+                    quote! { async move { Ok(#call.await) } }
+                } else {
+                    quote! { async move { Ok(#call) } }
+                }
+            })
+            .collect()
+    }
 }
 
 #[proc_macro_attribute]
-pub fn proxy(_attr: TokenStream, item: TokenStream) -> TokenStream {
-    let item_impl: ItemImpl = parse_macro_input!(item);
+pub fn proxy(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let item: Item = parse_macro_input!(item);
+    match item {
+        Item::Impl(item_impl) => expand_proxy(attr, &item_impl),
+        Item::Struct(item_struct) => expand_proxy_fields(attr, item_struct),
+        item => Err(syn::Error::new_spanned(
+            item,
+            "proxy expected an impl block or a struct",
+        )),
+    }
+    .unwrap_or_else(syn::Error::into_compile_error)
+    .into()
+}
+
+/// Options set with `#[proxy(...)]` on an impl block.
+#[derive(Default)]
+struct ProxyOptions {
+    /// Set on blocks that are merged into the proxy of another block.
+    part: bool,
+    /// The name of the part, which defaults to the name of the trait being implemented.
+    name: Option<Ident>,
+    /// The parts the proxy of this block is made of, besides the block itself.
+    parts: Vec<Ident>,
+}
+
+impl ProxyOptions {
+    fn parse(attr: TokenStream) -> syn::Result<Self> {
+        let mut options = Self::default();
+        let attribute_parser = syn::meta::parser(|meta| {
+            if meta.path.is_ident("part") {
+                options.part = true;
+                if meta.input.peek(Token![=]) {
+                    options.name = Some(meta.value()?.parse::<Ident>()?);
+                }
+                Ok(())
+            } else if meta.path.is_ident("parts") {
+                meta.parse_nested_meta(|part| {
+                    options.parts.push(part.path.require_ident()?.clone());
+                    Ok(())
+                })
+            } else {
+                Err(meta.error("unsupported proxy attribute"))
+            }
+        });
+        attribute_parser.parse(attr)?;
+
+        if options.part && !options.parts.is_empty() {
+            return Err(syn::Error::new(
+                Span::call_site(),
+                "a part of a proxy can't have parts of its own",
+            ));
+        }
+        Ok(options)
+    }
+}
+
+fn expand_proxy(attr: TokenStream, item_impl: &ItemImpl) -> syn::Result<TokenStream2> {
+    let options = ProxyOptions::parse(attr)?;
+
     let Type::Path(path) = &*item_impl.self_ty else {
-        panic!("proxy expected to find a path in impl");
+        return Err(syn::Error::new_spanned(
+            &item_impl.self_ty,
+            "proxy expected to find a path in impl",
+        ));
     };
 
-    if path.qself.is_some() {
-        panic!("can't proxy an impl with a qualified type");
+    if let Some(qself) = &path.qself {
+        return Err(syn::Error::new_spanned(
+            &qself.ty,
+            "can't proxy an impl with a qualified type",
+        ));
     }
 
-    if path.path.segments.len() > 1 {
-        panic!("can't proxy an impl with a multi-segment path")
-    }
+    // Generated items are named after the type, wherever it's defined
+    let Some(segment) = path.path.segments.last() else {
+        return Err(syn::Error::new_spanned(
+            &path.path,
+            "proxy expected to find a path in impl",
+        ));
+    };
 
-    let struct_name = path.path.segments.get(0).unwrap().ident.clone();
-    let trait_name = format_ident!("{}Proxy", struct_name);
+    let struct_name = segment.ident.clone();
+    let implemented = item_impl
+        .trait_
+        .as_ref()
+        .map(|(_, path, _)| path)
+        .and_then(|path| path.segments.last())
+        .map(|segment| &segment.ident);
+    let prefix = match (options.part, &options.name) {
+        (false, _) => struct_name.clone(),
+        (true, Some(name)) => format_ident!("{}{}", struct_name, name),
+        (true, None) => {
+            // Parts of trait impls are named after the trait unless they're given a name
+            let Some(implemented) = implemented else {
+                return Err(syn::Error::new(
+                    Span::call_site(),
+                    "parts made of inherent impls need a name, as in #[proxy(part = Name)]",
+                ));
+            };
+            format_ident!("{}{}", struct_name, implemented)
+        }
+    };
+    let trait_name = format_ident!("{}Proxy", prefix);
     let local_name = format_ident!("{}Local", struct_name);
+    let proxy_trait = proxy_trait(item_impl, &struct_name, &trait_name, &local_name, &options)?;
+    let proxy_factory = proxy_factory(item_impl, &format_ident!("{}Factory", prefix));
+
+    let self_ty = &item_impl.self_ty;
+    let (impl_generics, _, where_clause) = item_impl.generics.split_for_impl();
+    let predicate = where_clause
+        .map(|where_clause| where_clause.predicates.iter().collect::<Vec<_>>())
+        .unwrap_or_default();
+    let generic_argument = GenericParts::of(&item_impl.generics, &[]).argument;
+    let type_param = item_impl
+        .generics
+        .type_params()
+        .map(|param| &param.ident)
+        .collect::<Vec<_>>();
+
+    // Parts only add their methods, the rest is generated once with the block that lists them
+    if options.part {
+        return Ok(quote! {
+            #item_impl
+            #proxy_trait
+            #proxy_factory
+        });
+    }
+
+    let proxy_local = proxy_local(item_impl, &local_name);
     let client_name = format_ident!("{}Client", trait_name);
     let server_name = format_ident!("{}Server", trait_name);
+    Ok(quote! {
+        #item_impl
+        #proxy_trait
+        #proxy_factory
 
-    let functions = item_impl
-        .items
-        .iter()
-        .filter_map(|item| match item {
-            ImplItem::Fn(f) => {
-                if matches!(f.vis, Visibility::Public(_)) {
-                    Some(f)
-                } else {
-                    None
+        #proxy_local
+
+        impl #impl_generics ::combadge::AsHandle<#self_ty> for #self_ty
+        where
+            #(#predicate,)*
+            #(#type_param: 'static,)*
+        {
+            type Client = #client_name<::combadge::reexports::web_sys::MessagePort, #(#generic_argument),*>;
+            type Server = #server_name<::combadge::reexports::web_sys::MessagePort, #(#generic_argument),*>;
+
+            fn into_client(port: ::combadge::reexports::web_sys::MessagePort) -> Self::Client {
+                Self::Client::new_owning(port)
+            }
+
+            fn create_server(local: std::rc::Rc<std::cell::RefCell<#self_ty>>, port: ::combadge::reexports::web_sys::MessagePort) -> ::combadge::Revoker {
+                Self::create_notified_server(local, port, &::combadge::Notifier::default())
+            }
+
+            fn create_notified_server(local: std::rc::Rc<std::cell::RefCell<#self_ty>>, port: ::combadge::reexports::web_sys::MessagePort, notifier: &::combadge::Notifier) -> ::combadge::Revoker {
+                Self::Server::create_shared(#local_name::guarded(local, notifier), port)
+            }
+        }
+    })
+}
+
+/// Generates the local wrapper that proxies implement their remote traits on, which shares the
+/// proxied value with its owner and holds the watchers of its fields.
+fn proxy_local(item_impl: &ItemImpl, local_name: &Ident) -> TokenStream2 {
+    let self_ty = &item_impl.self_ty;
+    let (impl_generics, ty_generics, where_clause) = item_impl.generics.split_for_impl();
+    let predicate = where_clause
+        .map(|where_clause| where_clause.predicates.iter().collect::<Vec<_>>())
+        .unwrap_or_default();
+    let type_param = item_impl
+        .generics
+        .type_params()
+        .map(|param| &param.ident)
+        .collect::<Vec<_>>();
+
+    quote! {
+        struct #local_name #impl_generics #where_clause {
+            local: std::rc::Rc<std::cell::RefCell<#self_ty>>,
+            watchers: ::combadge::Watchers<#self_ty>,
+        }
+
+        impl #impl_generics #local_name #ty_generics
+        where
+            #(#predicate,)*
+            #(#type_param: 'static,)*
+        {
+            /// The owner may be using the value when a call arrives, so calls check that it's free
+            fn guarded(local: std::rc::Rc<std::cell::RefCell<#self_ty>>, notifier: &::combadge::Notifier) -> ::combadge::Guarded<Self> {
+                let watchers = ::combadge::Watchers::default();
+                let wrapper = std::rc::Rc::new(std::cell::RefCell::new(Self { local: local.clone(), watchers }));
+                notifier.on_notify(&wrapper, Self::check);
+                ::combadge::Guarded::sharing(wrapper, local)
+            }
+
+            fn check(&mut self) {
+                if let Ok(local) = self.local.try_borrow() {
+                    self.watchers.check(&local);
                 }
             }
-            _ => None,
-        })
+        }
+    }
+}
+
+/// The functions of an impl block that are callable remotely. Trait impls expose all of their
+/// functions, inherent impls only the public ones.
+fn proxied_functions(item_impl: &ItemImpl) -> impl Iterator<Item = &ImplItemFn> {
+    item_impl.items.iter().filter_map(|item| match item {
+        ImplItem::Fn(f) => {
+            if item_impl.trait_.is_some() || matches!(f.vis, Visibility::Public(_)) {
+                Some(f)
+            } else {
+                None
+            }
+        }
+        _ => None,
+    })
+}
+
+/// Whether `function` is an associated function returning a new `self_ty`.
+fn is_constructor(function: &ImplItemFn, self_ty: &Type) -> bool {
+    let ReturnType::Type(_, output) = &function.sig.output else {
+        return false;
+    };
+
+    function.sig.receiver().is_none()
+        && (matches!(&**output, Type::Path(path) if path.qself.is_none() && path.path.is_ident("Self"))
+            || output.to_token_stream().to_string() == self_ty.to_token_stream().to_string())
+}
+
+/// Generates a remote trait constructing the proxied type with the impl block's constructors, so
+/// that clients can have the value created where it's served. The worker serves it with the
+/// generated `{Factory}Local`, and each constructor is called as `create_{constructor}`.
+fn proxy_factory(item_impl: &ItemImpl, factory_name: &Ident) -> TokenStream2 {
+    let constructors = proxied_functions(item_impl)
+        .filter(|function| is_constructor(function, &item_impl.self_ty))
         .collect::<Vec<_>>();
+    if constructors.is_empty() {
+        return quote! {};
+    }
 
-    let argument = functions
+    let self_ty = &item_impl.self_ty;
+    let name = constructors
         .iter()
-        .map(|function| function.sig.inputs.iter().collect::<Vec<_>>())
+        .map(|function| &function.sig.ident)
         .collect::<Vec<_>>();
-
-    let name = functions
+    let create = name
         .iter()
-        .map(|function| function.sig.ident.clone())
+        .map(|name| format_ident!("create_{}", name))
         .collect::<Vec<_>>();
-
-    let non_receiver = argument
+    let input = constructors
         .iter()
-        .enumerate()
-        .map(|(index, arguments)| {
-            let non_receiver = arguments
-                .iter()
-                .filter_map(|arg| match arg {
-                    FnArg::Receiver(_) => None,
-                    FnArg::Typed(typed) => Some(typed.clone()),
-                })
-                .collect::<Vec<_>>();
+        .map(|function| {
+            let inputs = with_argument_names(&function.sig).inputs;
+            replace_self(inputs.into_token_stream(), &self_ty.to_token_stream())
+        })
+        .collect::<Vec<_>>();
+    let argument = constructors
+        .iter()
+        .map(|function| argument_names(&function.sig))
+        .collect::<Vec<_>>();
 
-            if non_receiver.len() == arguments.len() {
-                panic!(
-                    "expected {} to have a receiver (self parameter)",
-                    name[index]
-                )
+    let path = item_impl.trait_.as_ref().map_or_else(
+        || quote! { <#self_ty> },
+        |(_, path, _)| quote! { <#self_ty as #path> },
+    );
+    let local_name = format_ident!("{}Local", factory_name);
+    let (impl_generics, ty_generics, where_clause) = item_impl.generics.split_for_impl();
+    let predicate = where_clause
+        .map(|where_clause| where_clause.predicates.iter().collect::<Vec<_>>())
+        .unwrap_or_default();
+    let type_param = item_impl
+        .generics
+        .type_params()
+        .map(|param| &param.ident)
+        .collect::<Vec<_>>();
+
+    // Handles can only be made of values that can be served, which takes 'static parameters. The
+    // factory is public like the constructors it calls, so that workers can serve it from anywhere
+    quote! {
+        #[combadge]
+        pub trait #factory_name #impl_generics
+        where
+            #(#predicate,)*
+            #(#type_param: 'static,)*
+        {
+            #(
+                fn #create(&self, #input) -> ::combadge::Handle<#self_ty>;
+            )*
+        }
+
+        pub struct #local_name #impl_generics
+        where
+            #(#predicate,)*
+            #(#type_param: 'static,)*
+        {
+            phantom_: std::marker::PhantomData<fn() -> (#(#type_param,)*)>,
+        }
+
+        impl #impl_generics Default for #local_name #ty_generics
+        where
+            #(#predicate,)*
+            #(#type_param: 'static,)*
+        {
+            fn default() -> Self {
+                Self {
+                    phantom_: std::marker::PhantomData,
+                }
             }
+        }
 
-            non_receiver
-        })
+        impl #impl_generics #factory_name #ty_generics for #local_name #ty_generics
+        where
+            #(#predicate,)*
+            #(#type_param: 'static,)*
+        {
+            #(
+                fn #create(&self, #input) -> ::combadge::Handle<#self_ty> {
+                    ::combadge::Handle::from(#path::#name(#(#argument),*))
+                }
+            )*
+        }
+    }
+}
+
+/// Generates the remote trait for the methods of one impl block, implemented by the local wrapper
+/// forwarding to the proxied type.
+fn proxy_trait(
+    item_impl: &ItemImpl,
+    struct_name: &Ident,
+    trait_name: &Ident,
+    local_name: &Ident,
+    options: &ProxyOptions,
+) -> syn::Result<TokenStream2> {
+    let functions = proxied_functions(item_impl)
+        .filter(|function| !is_constructor(function, &item_impl.self_ty))
         .collect::<Vec<_>>();
 
-    let non_receiver_name = non_receiver
+    if let Some(function) = functions
         .iter()
-        .map(|non_receiver| {
-            non_receiver
-                .iter()
-                .filter_map(|item| match item.pat.as_ref() {
-                    Pat::Ident(ident) => Some(ident.clone()),
-                    _ => None,
-                })
-                .collect::<Vec<_>>()
-        })
+        .find(|function| function.sig.receiver().is_none())
+    {
+        return Err(syn::Error::new_spanned(
+            &function.sig,
+            format!(
+                "expected {} to have a receiver (self parameter)",
+                function.sig.ident
+            ),
+        ));
+    }
+
+    let non_receiver_name = functions
+        .iter()
+        .map(|function| argument_names(&function.sig))
         .collect::<Vec<_>>();
 
-    let output = functions
+    // `Self` means the proxied type in the impl, but the local wrapper in the generated trait
+    let self_ty = item_impl.self_ty.to_token_stream();
+    let input = functions
         .iter()
-        .map(|function| function.sig.output.clone())
+        .map(|function| {
+            let inputs = with_argument_names(&function.sig).inputs;
+            replace_self(inputs.into_token_stream(), &self_ty)
+        })
         .collect::<Vec<_>>();
 
-    let return_type = output
+    let return_type = functions
         .iter()
-        .map(|output| match output {
+        .map(|function| match &function.sig.output {
             ReturnType::Default => quote! { () },
-            ReturnType::Type(_, t) => quote! { #t },
+            ReturnType::Type(_, t) => replace_self(t.to_token_stream(), &self_ty),
         })
         .collect::<Vec<_>>();
 
@@ -652,52 +3420,161 @@ pub fn proxy(_attr: TokenStream, item: TokenStream) -> TokenStream {
         .map(|function| function.sig.ident.clone())
         .collect::<Vec<_>>();
 
-    let argument = functions
+    let path = item_impl.trait_.as_ref().map_or_else(
+        || quote! { <#self_ty> },
+        |(_, path, _)| quote! { <#self_ty as #path> },
+    );
+
+    // Calls that can change the value let watchers of its fields know. The server has already
+    // checked that the value can be borrowed, so these won't panic even though the owner shares it
+    let call = functions
         .iter()
-        .map(|function| function.sig.inputs.iter().collect::<Vec<_>>())
+        .zip(name.iter().zip(&non_receiver_name))
+        .map(
+            |(function, (name, non_receiver_name))| match function.sig.receiver() {
+                Some(receiver) if receiver.mutability.is_some() => quote! {
+                    let result = #path::#name(&mut *self.local.borrow_mut(), #(#non_receiver_name),*);
+                    self.watchers.check(&self.local.borrow());
+                    result
+                },
+                _ => quote! { #path::#name(&*self.local.borrow(), #(#non_receiver_name),*) },
+            },
+        )
         .collect::<Vec<_>>();
 
-    quote! {
-        #item_impl
+    let (impl_generics, ty_generics, where_clause) = item_impl.generics.split_for_impl();
 
+    let supertraits = options
+        .parts
+        .iter()
+        .map(|part| format_ident!("{}{}Proxy", struct_name, part))
+        .collect::<Vec<_>>();
+    let supertraits = if supertraits.is_empty() {
+        quote! {}
+    } else {
+        quote! { : #(#supertraits #ty_generics)+* }
+    };
+
+    Ok(quote! {
         #[combadge]
-        trait #trait_name {
+        trait #trait_name #impl_generics #supertraits #where_clause {
             #(
-                fn #name(#(#argument),*) -> #return_type;
+                fn #name(#input) -> #return_type;
             )*
         }
 
-        struct #local_name {
-            local: #struct_name
-        }
-
-        impl #local_name {
-            fn new(local: #struct_name) -> Self {
-                Self { local }
-            }
-        }
-
-        impl #trait_name for #local_name {
+        impl #impl_generics #trait_name #ty_generics for #local_name #ty_generics #where_clause {
             #(
-                fn #name(#(#argument),*) -> #return_type {
-                    self.local.#name(#(#non_receiver_name),*)
+                fn #name(#input) -> #return_type {
+                    #call
                 }
             )*
         }
+    })
+}
 
-        impl ::combadge::AsHandle<#struct_name> for #struct_name {
-            type Client = #client_name<::combadge::reexports::web_sys::MessagePort>;
-            type Server = #server_name<::combadge::reexports::web_sys::MessagePort>;
+/// Generates a part of a proxy with remote accessors for the struct's fields marked with
+/// `#[proxy(get)]`, `#[proxy(set)]` or `#[proxy(watch)]`.
+fn expand_proxy_fields(
+    attr: TokenStream,
+    mut item_struct: ItemStruct,
+) -> syn::Result<TokenStream2> {
+    let options = ProxyOptions::parse(attr)?;
+    if let Some(part) = options.parts.first() {
+        return Err(syn::Error::new_spanned(
+            part,
+            "a struct's fields are a part of a proxy, so they can't have parts of their own",
+        ));
+    }
 
-            fn into_client(port: ::combadge::reexports::web_sys::MessagePort) -> Self::Client {
-                Self::Client::new(port)
-            }
+    let struct_name = &item_struct.ident;
+    let part = options.name.unwrap_or_else(|| format_ident!("Fields"));
+    let trait_name = format_ident!("{}{}Proxy", struct_name, part);
+    let local_name = format_ident!("{}Local", struct_name);
 
-            fn create_server(local: #struct_name, port: ::combadge::reexports::web_sys::MessagePort)  {
-                Self::Server::create(#local_name::new(local), port);
+    // As with methods, the server checks that the value can be borrowed before each call
+    let mut signature = Vec::new();
+    let mut body = Vec::new();
+    for field in &mut item_struct.fields {
+        let mut get = false;
+        let mut set = false;
+        let mut watch = false;
+        for attribute in &field.attrs {
+            if !attribute.path().is_ident("proxy") {
+                continue;
             }
+
+            attribute.parse_nested_meta(|meta| {
+                if meta.path.is_ident("get") {
+                    get = true;
+                } else if meta.path.is_ident("set") {
+                    set = true;
+                } else if meta.path.is_ident("watch") {
+                    watch = true;
+                } else {
+                    return Err(meta.error("expected get, set or watch"));
+                }
+                Ok(())
+            })?;
         }
+        field
+            .attrs
+            .retain(|attribute| !attribute.path().is_ident("proxy"));
 
+        if !(get || set || watch) {
+            continue;
+        }
+        let Some(ident) = &field.ident else {
+            return Err(syn::Error::new_spanned(
+                field,
+                "only named fields can be accessed remotely",
+            ));
+        };
+        let ty = &field.ty;
+
+        if get {
+            let getter = format_ident!("get_{}", ident);
+            signature.push(quote! { fn #getter(&self) -> #ty });
+            body.push(quote! { Clone::clone(&self.local.borrow().#ident) });
+        }
+        if set {
+            let setter = format_ident!("set_{}", ident);
+            signature.push(quote! { fn #setter(&mut self, value: #ty) });
+            body.push(quote! {
+                self.local.borrow_mut().#ident = value;
+                self.watchers.check(&self.local.borrow());
+            });
+        }
+        if watch {
+            let watcher = format_ident!("watch_{}", ident);
+            signature.push(quote! {
+                fn #watcher(&mut self, callback: ::combadge::Callback<(#ty,), ()>)
+            });
+            body.push(quote! {
+                self.watchers.watch(&self.local.borrow(), |local| &local.#ident, move |value| {
+                    ::combadge::prelude::Call1::call(&callback, value)
+                });
+            });
+        }
     }
-    .into()
+
+    let (impl_generics, ty_generics, where_clause) = item_struct.generics.split_for_impl();
+    Ok(quote! {
+        #item_struct
+
+        #[combadge]
+        trait #trait_name #impl_generics #where_clause {
+            #(
+                #signature;
+            )*
+        }
+
+        impl #impl_generics #trait_name #ty_generics for #local_name #ty_generics #where_clause {
+            #(
+                #signature {
+                    #body
+                }
+            )*
+        }
+    })
 }
@@ -1,5 +1,5 @@
 use std::any::type_name;
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::collections::VecDeque;
 use std::future::{ready, Future};
 use std::marker::PhantomData;
@@ -16,8 +16,8 @@ use wasm_bindgen::prelude::*;
 use wasm_bindgen_futures::{spawn_local, JsFuture};
 use web_sys::{MessageChannel, MessageEvent, MessagePort};
 
-use crate::message::PostTuple;
-use crate::{log_error, Error, Message, Post, Transfer};
+use crate::message::{PostTuple, Revoked};
+use crate::{log_error, Error, Message, Post, Revoker, Transfer};
 
 type AsyncReturn<R> = Pin<Box<dyn Future<Output = R> + 'static>>;
 type AsyncReturnWithError<R> = Pin<Box<dyn Future<Output = Result<R, Error>> + 'static>>;
@@ -28,10 +28,18 @@ trait Responder {
 
 build_responder!(7);
 
+impl<T: Responder + ?Sized> Responder for Rc<T> {
+    fn respond(&self, arguments: Array, port: MessagePort) -> Result<(), Error> {
+        (**self).respond(arguments, port)
+    }
+}
+
 struct CallbackServer {
     /// The server holds a reference to itself so it can keep the Closure alive.
     /// Once it receives the drop message, it releases this reference so the whole server is dropped.
     phylactery: Option<Rc<RefCell<Self>>>,
+    responder: Box<dyn Responder>,
+    port: MessagePort,
     #[expect(
         dead_code,
         reason = "We hold onto this closure's memory until the server is dropped"
@@ -40,7 +48,9 @@ struct CallbackServer {
 }
 
 impl CallbackServer {
-    pub fn create<T: Responder + 'static>(callback: T) -> Result<MessagePort, Error> {
+    /// Serves `callback`, returning the port to hand to the remote and a revoker that stops
+    /// serving it.
+    pub fn create<T: Responder + 'static>(callback: T) -> Result<(MessagePort, Revoker), Error> {
         let channel = MessageChannel::new().map_err(|error| Error::CreationFailed {
             type_name: String::from("MessageChannel"),
             error: format!("{error:?}"),
@@ -48,7 +58,6 @@ impl CallbackServer {
 
         let server = Rc::new_cyclic(|weak_self: &Weak<RefCell<Self>>| {
             let cloned_weak = weak_self.clone();
-            let server_port = channel.port1();
             let on_message = Closure::wrap(Box::new(move |message: MessageEvent| {
                 let payload: Array = message.data().into();
                 let Some(operation) = payload.shift().as_string() else {
@@ -56,19 +65,27 @@ impl CallbackServer {
                     return;
                 };
 
+                let Some(server) = Weak::upgrade(&cloned_weak) else {
+                    log_error!("failed to upgrade weak CallbackServer in message callback");
+                    return;
+                };
+
                 match operation.as_str() {
                     "call" => {
-                        if let Err(error) = callback.respond(payload, server_port.clone()) {
+                        let Ok(server) = server.try_borrow() else {
+                            log_error!("failed to borrow CallbackServer to call it");
+                            return;
+                        };
+
+                        if let Err(error) = server.responder.respond(payload, server.port.clone()) {
                             log_error!("failed to respond to CallbackServer call: {error}");
                         }
                     }
                     "drop" => {
-                        if let Some(client) = Weak::upgrade(&cloned_weak) {
-                            if let Ok(mut client) = client.try_borrow_mut() {
-                                client.phylactery = None;
-                            } else {
-                                log_error!("failed to borrow CallbackServer to drop it");
-                            }
+                        if let Ok(mut server) = server.try_borrow_mut() {
+                            server.release();
+                        } else {
+                            log_error!("failed to borrow CallbackServer to drop it");
                         }
                     }
                     _ => {
@@ -83,19 +100,88 @@ impl CallbackServer {
 
             RefCell::new(Self {
                 phylactery: None,
+                responder: Box::new(callback),
+                port: channel.port1(),
                 on_message,
             })
         });
 
         server.borrow_mut().phylactery = Some(server.clone());
 
-        Ok(channel.port2())
+        let revoker = Revoker::default();
+        let weak_server = Rc::downgrade(&server);
+        revoker.on_revoke(move || {
+            // The callback may revoke itself while the server is borrowed to call it, so wait for
+            // the call to finish
+            spawn_local(async move {
+                let Some(server) = Weak::upgrade(&weak_server) else {
+                    return;
+                };
+
+                let Ok(mut server) = server.try_borrow_mut() else {
+                    log_error!("failed to borrow CallbackServer to revoke it");
+                    return;
+                };
+
+                server.revoke();
+            });
+        });
+
+        Ok((channel.port2(), revoker))
+    }
+
+    /// Lets the client know the callback was revoked and stops serving it. Calls that were already
+    /// on their way are never answered, so the client fails them itself.
+    fn revoke(&mut self) {
+        if let Err(error) = self.port.post_message(&Revoked::to_js_value()) {
+            log_error!("error posting revocation to CallbackClient: {error:?}");
+        }
+        self.release();
+    }
+
+    /// Stops listening and lets go of the server, which drops the callback.
+    fn release(&mut self) {
+        self.port.set_onmessage(None);
+        self.port.close();
+        self.phylactery = None;
+    }
+}
+
+/// The port of a callback received from a remote, kept so the callback can be passed on to
+/// another remote instead of being called.
+#[derive(Clone)]
+struct Forwarding {
+    port: MessagePort,
+    forwarded: Rc<Cell<bool>>,
+    results: Rc<RefCell<VecDeque<Function>>>,
+}
+
+impl Forwarding {
+    /// Hands the port over to whoever it's posted to. The callback's client stops listening on it
+    /// and no longer tells the server to drop the callback when it's dropped. This fails while calls
+    /// made through the callback are waiting, as their results would reach the port's new owner.
+    fn forward(self) -> Result<MessagePort, Error> {
+        if !self
+            .results
+            .try_borrow()
+            .is_ok_and(|results| results.is_empty())
+        {
+            return Err(Error::CallbackFailed {
+                error: String::from("can't forward a callback while calls to it are waiting"),
+            });
+        }
+
+        self.forwarded.set(true);
+        self.port.set_onmessage(None);
+        Ok(self.port)
     }
 }
 
 struct CallbackClient<Args, Return> {
     _phantom: PhantomData<(Args, Return)>,
     port: MessagePort,
+    forwarded: Rc<Cell<bool>>,
+    revoked: Rc<Cell<bool>>,
     #[expect(
         dead_code,
         reason = "We hold onto this closure's memory until the server is dropped"
@@ -111,6 +197,8 @@ where
     fn new(port: MessagePort) -> Self {
         let results: Rc<RefCell<VecDeque<Function>>> = Rc::default();
         let cloned_results = results.clone();
+        let revoked: Rc<Cell<bool>> = Rc::default();
+        let cloned_revoked = revoked.clone();
 
         let on_message = Closure::wrap(Box::new(move |message: MessageEvent| {
             let Ok(mut results) = cloned_results.try_borrow_mut() else {
@@ -118,12 +206,28 @@ where
                 return;
             };
 
+            // Fail everything that's waiting. The server has already stopped listening, so there's
+            // no need to tell it to drop the callback
+            let data = message.data();
+            if Revoked::is_revoked(&data) {
+                if cloned_revoked.replace(true) {
+                    return;
+                }
+
+                for send_result in results.drain(..) {
+                    if let Err(error) = send_result.call1(&JsValue::NULL, &data) {
+                        log_error!("error while failing calls to revoked callback: {error:?}");
+                    }
+                }
+                return;
+            }
+
             let Some(send_result) = results.pop_front() else {
                 log_error!("no result function found in CallbackClient");
                 return;
             };
 
-            if let Err(error) = send_result.call1(&JsValue::NULL, &message.data()) {
+            if let Err(error) = send_result.call1(&JsValue::NULL, &data) {
                 log_error!("error while calling send_result in CallbackClient::call: {error:?}");
             }
         }) as Box<dyn Fn(MessageEvent)>);
@@ -133,12 +237,33 @@ where
         Self {
             _phantom: PhantomData,
             port,
+            forwarded: Rc::default(),
+            revoked,
             on_message,
             results,
         }
     }
 
+    fn forwarding(&self) -> Forwarding {
+        Forwarding {
+            port: self.port.clone(),
+            forwarded: self.forwarded.clone(),
+            results: self.results.clone(),
+        }
+    }
+
     fn call(&self, args: Args) -> AsyncReturnWithError<Return> {
+        if self.revoked.get() {
+            return Box::pin(ready(Err(Error::Revoked)));
+        }
+
+        // A clone of the callback passed the port on to another remote
+        if self.forwarded.get() {
+            return Box::pin(ready(Err(Error::CallbackFailed {
+                error: String::from("callback was forwarded to another remote"),
+            })));
+        }
+
         let mut send_result = None;
         let promise = Promise::new(&mut |resolve, _reject| {
             send_result = Some(resolve);
@@ -167,7 +292,13 @@ where
         Box::pin(async { post }.and_then(|()| {
             JsFuture::from(promise).map(|result| {
                 result
-                    .map(|result| Post::from_js_value(result))
+                    .map(|result| {
+                        if Revoked::is_revoked(&result) {
+                            Err(Error::Revoked)
+                        } else {
+                            Post::from_js_value(result)
+                        }
+                    })
                     .map_err(|error| Error::CallbackFailed {
                         error: format!("promise rejected: {error:?}"),
                     })
@@ -186,6 +317,10 @@ build_to_closure!(7);
 
 impl<Args, Return> Drop for CallbackClient<Args, Return> {
     fn drop(&mut self) {
+        if self.forwarded.get() || self.revoked.get() {
+            return;
+        }
+
         if let Err(error) = self
             .port
             .post_message(&Array::of1(&JsValue::from_str("drop")))
@@ -209,14 +344,49 @@ impl<T> CallbackTypes for T {
 
 build_callback_types!(7);
 
+/// Clones share the same callback. A callback received from a remote tells its server to drop it
+/// once the last clone is dropped.
 pub struct Callback<Args, Return: 'static> {
-    local: Option<<(Args, Return) as CallbackTypes>::Local>,
-    async_local: Option<<(Args, Return) as CallbackTypes>::AsyncLocal>,
-    remote: Option<<(Args, Return) as CallbackTypes>::Remote>,
+    local: Option<Rc<<(Args, Return) as CallbackTypes>::Local>>,
+    async_local: Option<Rc<<(Args, Return) as CallbackTypes>::AsyncLocal>>,
+    remote: Option<Rc<<(Args, Return) as CallbackTypes>::Remote>>,
+    forwarding: Option<Forwarding>,
+    revoker: Option<Revoker>,
+}
+
+impl<Args, Return> Clone for Callback<Args, Return> {
+    fn clone(&self) -> Self {
+        Self {
+            local: self.local.clone(),
+            async_local: self.async_local.clone(),
+            remote: self.remote.clone(),
+            forwarding: self.forwarding.clone(),
+            revoker: self.revoker.clone(),
+        }
+    }
 }
 
 build_callback_from_closure!(7);
 
+impl<Args, Return> Callback<Args, Return> {
+    /// Lets `revoker` stop serving the callback once this is sent. Callbacks received from a
+    /// remote can only be revoked by whoever serves them.
+    pub fn revocable_by(&mut self, revoker: &Revoker) {
+        self.revoker = Some(revoker.clone());
+    }
+
+    fn serve<T: Responder + 'static>(
+        responder: T,
+        revoker: Option<Revoker>,
+    ) -> Result<JsValue, Error> {
+        let (port, served) = CallbackServer::create(responder)?;
+        if let Some(revoker) = revoker {
+            revoker.on_revoke(move || served.revoke());
+        }
+        Ok(port.into())
+    }
+}
+
 impl<Args: 'static, Return: 'static> Post for Callback<Args, Return>
 where
     Message: PostTuple<Args>,
@@ -229,14 +399,20 @@ where
 
     fn from_js_value(value: JsValue) -> Result<Self, Error> {
         let client = CallbackClient::<Args, Return>::new(value.into());
-        Ok(client.to_closure().into())
+        let forwarding = client.forwarding();
+        let mut callback: Self = client.to_closure().into();
+        callback.forwarding = Some(forwarding);
+        Ok(callback)
     }
 
     fn to_js_value(self) -> Result<JsValue, Error> {
-        if let Some(local) = self.local {
-            CallbackServer::create(local).map(JsValue::from)
+        if let Some(forwarding) = self.forwarding {
+            // Dropping the remote closure afterwards drops our client, which leaves the port alone
+            forwarding.forward().map(Into::into)
+        } else if let Some(local) = self.local {
+            Self::serve(local, self.revoker)
         } else if let Some(async_local) = self.async_local {
-            CallbackServer::create(async_local).map(JsValue::from)
+            Self::serve(async_local, self.revoker)
         } else {
             return Err(Error::SerializeFailed {
                 type_name: String::from(type_name::<Self>()),
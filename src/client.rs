@@ -1,14 +1,17 @@
 use std::cell::RefCell;
+use std::collections::BTreeMap;
 use std::future::{ready, Future};
 use std::rc::{Rc, Weak};
 
 use futures::{FutureExt, TryFutureExt};
-use js_sys::{Array, Function, Promise};
+use js_sys::{Function, Promise};
 use wasm_bindgen::prelude::*;
-use wasm_bindgen_futures::JsFuture;
-use web_sys::{MessageChannel, MessageEvent};
+use wasm_bindgen_futures::{spawn_local, JsFuture};
+use web_sys::{MessageChannel, MessageEvent, MessageEventInit, MessagePort};
 
-use crate::{log_error, Error, Message, Port, Post};
+use crate::handshake::Handshake;
+use crate::message::{Batch, Failed, Release, Revoked};
+use crate::{log_error, AsHandle, Error, Message, Port, Post};
 
 #[derive(Debug)]
 pub struct Client<P: Port> {
@@ -18,61 +21,122 @@ pub struct Client<P: Port> {
     )]
     on_message: Closure<dyn Fn(MessageEvent)>,
     pub port: P,
-    server_ready: bool,
+    handshake: Handshake,
+    remote: Option<Handshake>,
     on_ready: Vec<Function>,
+    weak_self: Weak<RefCell<Self>>,
+    batching: bool,
+    batch: Batch,
+    explicit_batch: Option<Batch>,
+    deferred_batches: Vec<Batch>,
+    release_on_drop: bool,
+    /// Set once the server tells us it's gone, to fail calls with
+    failure: Option<JsValue>,
+    /// Resolvers for the calls awaiting a response, so that they can be failed along with the client
+    pending: Rc<RefCell<BTreeMap<u64, Function>>>,
+    next_call: u64,
 }
 
 impl<P: Port + 'static> Client<P> {
-    pub fn new(port: P) -> Rc<RefCell<Self>> {
+    /// Creates a client on `port`, handshaking with a server whose trait has the given signature.
+    pub fn new(port: P, signature: u64) -> Rc<RefCell<Self>> {
+        let handshake = Handshake::new(signature);
+
         Rc::new_cyclic(|weak_self: &Weak<RefCell<Self>>| {
             let cloned_weak_self = weak_self.clone();
             let on_message = Closure::new(move |event: MessageEvent| {
-                if let Some(message) = event.data().as_string() {
-                    if message == "*handshake" {
-                        let Some(client) = Weak::upgrade(&cloned_weak_self) else {
-                            log_error!("failed to upgrade weak client in message callback");
+                let data = event.data();
+                // The server is gone, either because its value was revoked or because it will never
+                // be served
+                if failure(&data).is_some() {
+                    let Some(client) = Weak::upgrade(&cloned_weak_self) else {
+                        log_error!("failed to upgrade weak client in message callback");
+                        return;
+                    };
+
+                    // As with the handshake, don't call the callbacks until we drop the borrow
+                    let waiting = {
+                        let Ok(mut client) = client.try_borrow_mut() else {
+                            log_error!("failed to borrow client in message callback");
                             return;
                         };
 
-                        // Contain the borrow to a smaller scope so that the callbacks aren't called until
-                        // after we drop it
-                        let on_ready = {
-                            let Ok(mut client) = client.try_borrow_mut() else {
-                                log_error!("failed to borrow client in message callback");
-                                return;
-                            };
-
-                            client.server_ready = true;
-                            client.on_ready.drain(..).collect::<Vec<_>>()
-                        };
+                        client.fail(data.clone())
+                    };
 
-                        for on_ready in on_ready {
-                            if let Err(error) = on_ready.call0(&JsValue::NULL) {
-                                log_error!("failed to call on_ready callback in message callback: {error:?}");
-                            }
+                    for waiting in waiting {
+                        if let Err(error) = waiting.call1(&JsValue::NULL, &data) {
+                            log_error!("failed to fail call in message callback: {error:?}");
                         }
                     }
+                    return;
+                }
+
+                let Some(remote) = Handshake::from_js_value(&data) else {
+                    return;
+                };
+
+                let Some(client) = Weak::upgrade(&cloned_weak_self) else {
+                    log_error!("failed to upgrade weak client in message callback");
+                    return;
+                };
+
+                // Contain the borrow to a smaller scope so that the callbacks aren't called until
+                // after we drop it
+                let on_ready = {
+                    let Ok(mut client) = client.try_borrow_mut() else {
+                        log_error!("failed to borrow client in message callback");
+                        return;
+                    };
+
+                    client.remote = Some(remote);
+                    client.send_deferred();
+                    client.on_ready.drain(..).collect::<Vec<_>>()
+                };
+
+                for on_ready in on_ready {
+                    if let Err(error) = on_ready.call1(&JsValue::NULL, &data) {
+                        log_error!("failed to call on_ready callback in message callback: {error:?}");
+                    }
                 }
             });
 
             port.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
 
-            if let Err(error) = port.post_message(&Array::of1(&JsValue::from_str("*handshake"))) {
+            if let Err(error) = port.post_message(&handshake.to_js_value()) {
                 log_error!("error sending handshake: {error:?}");
             }
 
             RefCell::new(Self {
                 on_message,
                 port,
-                server_ready: false,
+                handshake,
+                remote: None,
                 on_ready: Vec::new(),
+                weak_self: weak_self.clone(),
+                batching: false,
+                batch: Batch::default(),
+                explicit_batch: None,
+                deferred_batches: Vec::new(),
+                release_on_drop: false,
+                failure: None,
+                pending: Rc::new(RefCell::new(BTreeMap::new())),
+                next_call: 0,
             })
         })
     }
 
-    pub fn wait_for_server(&mut self) -> impl Future<Output = ()> {
-        if self.server_ready {
-            return ready(()).left_future();
+    /// Resolves once the server has answered the handshake, failing with
+    /// [`Error::ProtocolMismatch`] if it speaks a different protocol version or serves a different
+    /// trait.
+    pub fn wait_for_server(&mut self) -> impl Future<Output = Result<(), Error>> {
+        let handshake = self.handshake;
+        if let Some(error) = self.failure.as_ref().and_then(failure) {
+            return ready(Err(error)).left_future();
+        }
+
+        if let Some(remote) = self.remote {
+            return ready(handshake.check(remote)).left_future();
         }
 
         let mut on_ready = None;
@@ -84,31 +148,155 @@ impl<P: Port + 'static> Client<P> {
             self.on_ready.push(on_ready)
         }
 
-        let future = JsFuture::from(promise).map(|result| {
-            result.map_or_else(
-                |error| {
-                    log_error!("error in wait_for_server future: {error:?}");
-                },
-                |_| (),
-            )
+        let future = JsFuture::from(promise).map(move |result| {
+            result
+                .map_err(|error| Error::ReceiveFailed {
+                    error: format!("error in wait_for_server future: {error:?}"),
+                })
+                .and_then(|remote| {
+                    // The client was dropped before the server answered
+                    if remote.is_undefined() {
+                        return Err(Error::ClientUnavailable);
+                    }
+
+                    // The server is gone
+                    if let Some(error) = failure(&remote) {
+                        return Err(error);
+                    }
+
+                    Handshake::from_js_value(&remote).ok_or_else(|| Error::ReceiveFailed {
+                        error: format!("expected handshake but received {remote:?}"),
+                    })
+                })
+                .and_then(|remote| handshake.check(remote))
         });
 
         future.right_future()
     }
 
+    /// Tells the server to release the value it serves once this client is dropped, for clients
+    /// that own the value, like those made from a [`Handle`](crate::Handle).
+    pub const fn release_on_drop(&mut self) {
+        self.release_on_drop = true;
+    }
+
+    /// Fails calls from now on, returning the resolvers of those waiting for the server or for a
+    /// response so that the caller can fail them with `failure` once it drops its borrow.
+    fn fail(&mut self, failure: JsValue) -> Vec<Function> {
+        self.failure = Some(failure);
+        self.release_on_drop = false;
+        let pending = std::mem::take(&mut *self.pending.borrow_mut());
+        self.on_ready
+            .drain(..)
+            .chain(pending.into_values())
+            .collect()
+    }
+
+    /// When batching is enabled, messages sent within the same microtask are coalesced into a
+    /// single `postMessage`. Disabling batching flushes any pending messages immediately.
+    pub fn set_batching(&mut self, batching: bool) {
+        self.batching = batching;
+        if !batching {
+            self.flush();
+        }
+    }
+
+    /// Starts collecting messages into a batch that is sent by [`Client::end_batch`]. Returns
+    /// `false` without doing anything if a batch is already in progress, in which case messages
+    /// join that batch instead.
+    pub fn begin_batch(&mut self) -> bool {
+        if self.explicit_batch.is_some() {
+            return false;
+        }
+
+        self.explicit_batch = Some(Batch::default());
+        true
+    }
+
+    /// Sends the batch started by [`Client::begin_batch`] as a single message, once the server is
+    /// ready. With `stop_on_error`, the server skips the rest of the batch once a call fails to
+    /// dispatch, as described on [`Batch::stop_on_error`](crate::message::Batch::stop_on_error).
+    pub fn end_batch(&mut self, stop_on_error: bool) {
+        let Some(mut batch) = self.explicit_batch.take() else {
+            return;
+        };
+
+        if stop_on_error {
+            batch.stop_on_error();
+        }
+
+        match self.remote {
+            Some(remote) if self.handshake.check(remote).is_ok() => self.send_batch(batch),
+            // The calls report the mismatch when they wait for the server, so there's no point
+            // sending them
+            Some(_) => (),
+            None => self.deferred_batches.push(batch),
+        }
+    }
+
+    fn post(&mut self, message: &JsValue, transfer: &JsValue) -> Result<(), Error> {
+        if let Some(batch) = &mut self.explicit_batch {
+            batch.push(message, transfer);
+            return Ok(());
+        }
+
+        if !self.batching {
+            return self
+                .port
+                .post_message_with_transfer(message, transfer)
+                .map_err(|error| Error::PostFailed {
+                    error: format!("error posting message in Client send_message: {error:?}"),
+                });
+        }
+
+        if self.batch.is_empty() {
+            let weak_self = self.weak_self.clone();
+            spawn_local(async move {
+                let Some(client) = Weak::upgrade(&weak_self) else {
+                    log_error!("failed to upgrade weak client to flush batch");
+                    return;
+                };
+
+                let Ok(mut client) = client.try_borrow_mut() else {
+                    log_error!("failed to borrow client to flush batch");
+                    return;
+                };
+
+                client.flush();
+            });
+        }
+
+        self.batch.push(message, transfer);
+        Ok(())
+    }
+
     pub fn send_message<T>(
         &mut self,
         mut message: Message,
     ) -> impl Future<Output = Result<T, Error>> {
-        let channel = MessageChannel::new().map_err(|error| Error::CreationFailed {
-            type_name: String::from("MessageChannel"),
-            error: format!("{error:?}"),
-        });
+        let channel = self.failure.as_ref().and_then(failure).map_or_else(
+            || {
+                MessageChannel::new().map_err(|error| Error::CreationFailed {
+                    type_name: String::from("MessageChannel"),
+                    error: format!("{error:?}"),
+                })
+            },
+            Err,
+        );
 
+        let id = self.next_call;
+        self.next_call += 1;
+        let pending = self.pending.clone();
         let promise = channel.and_then(|channel| {
             let promise = Promise::new(&mut |resolve, _reject| {
+                pending.borrow_mut().insert(id, resolve);
+                let pending = pending.clone();
                 let callback = Closure::once_into_js(move |message: MessageEvent| {
-                    let _ = resolve.call1(&JsValue::NULL, &message.data());
+                    // The call may have been failed already
+                    let resolve = pending.borrow_mut().remove(&id);
+                    if let Some(resolve) = resolve {
+                        let _ = resolve.call1(&JsValue::NULL, &message.data());
+                    }
                 });
 
                 channel
@@ -116,17 +304,16 @@ impl<P: Port + 'static> Client<P> {
                     .set_onmessage(Some(callback.as_ref().unchecked_ref()));
             });
 
-            message.post(channel.port1()).and_then(|()| {
-                message
-                    .send(|message, transfer| {
-                        self.port
-                            .post_message_with_transfer(message, transfer)
-                            .map_err(|error| Error::PostFailed {
-                                error: format!("error posting message in Client send_message: {error:?}"),
-                            })
-                    })
-                    .and_then(|()| Ok(promise))
-            })
+            message
+                .post(channel.port1())
+                .and_then(|()| {
+                    message
+                        .send(|message, transfer| self.post(message, transfer))
+                        .and_then(|()| Ok(promise))
+                })
+                .inspect_err(|_| {
+                    pending.borrow_mut().remove(&id);
+                })
         });
 
         async {
@@ -136,11 +323,188 @@ impl<P: Port + 'static> Client<P> {
                         .map_err(|error| Error::ReceiveFailed {
                             error: format!("{error:?}"),
                         })
-                        .and_then(|result| T::from_js_value(result))
+                        .and_then(|result| {
+                            failure(&result).map_or_else(|| T::from_js_value(result), Err)
+                        })
                 })
             })
         }
         .try_flatten()
         .into_future()
     }
+
+    /// Sends `message` once the server is ready, resolving to the procedure's result.
+    ///
+    /// # Errors
+    ///
+    /// Resolves to an error if the client is already borrowed, the message can't be built or sent,
+    /// or the call fails.
+    #[expect(
+        clippy::future_not_send,
+        reason = "Clients hold onto their port, which can't be sent between threads"
+    )]
+    pub fn call<T>(
+        client: &Rc<RefCell<Self>>,
+        message: Result<Message, Error>,
+    ) -> impl Future<Output = Result<T, Error>> {
+        let server_ready = match client
+            .try_borrow_mut()
+            .map_err(|_| Error::ClientUnavailable)
+        {
+            Ok(mut client) => client.wait_for_server().left_future(),
+            Err(error) => ready(Err(error)).right_future(),
+        };
+
+        let client = client.clone();
+        server_ready.then(move |result| {
+            let response = result.and(message).and_then(|message| {
+                client
+                    .try_borrow_mut()
+                    .map_err(|_| Error::ClientUnavailable)
+                    .map(|mut client| client.send_message::<T>(message))
+            });
+            async { response }.try_flatten()
+        })
+    }
+
+    /// Like [`Client::call`], but posts `message` immediately rather than when the future is first
+    /// polled, so that it lands in the batch currently being built.
+    ///
+    /// # Errors
+    ///
+    /// Resolves to an error for the same reasons as [`Client::call`].
+    #[expect(
+        clippy::future_not_send,
+        reason = "Clients hold onto their port, which can't be sent between threads"
+    )]
+    pub fn queue<T>(
+        client: &Rc<RefCell<Self>>,
+        message: Result<Message, Error>,
+    ) -> impl Future<Output = Result<T, Error>> {
+        let queued = client
+            .try_borrow_mut()
+            .map_err(|_| Error::ClientUnavailable)
+            .and_then(|mut client| {
+                let server_ready = client.wait_for_server();
+                message.map(|message| (server_ready, client.send_message::<T>(message)))
+            });
+
+        async move {
+            let (server_ready, response) = queued?;
+            server_ready.await?;
+            response.await
+        }
+    }
+
+    /// Calls a procedure that returns a [`Handle`], returning a client for the result right away.
+    /// The server serves the result on a port we supply, so calls made on the returned client are
+    /// queued until the result exists rather than waiting for a round trip. If the call fails, so
+    /// do the returned client's calls.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the message can't be built or a port can't be created for the result. Failures
+    /// after that fail the returned client's calls instead.
+    pub fn pipeline<T: AsHandle<T> + ?Sized>(
+        client: &Rc<RefCell<Self>>,
+        message: Result<Message, Error>,
+    ) -> Result<T::Client, Error> {
+        let channel = MessageChannel::new().map_err(|error| Error::CreationFailed {
+            type_name: String::from("MessageChannel"),
+            error: format!("{error:?}"),
+        })?;
+
+        let mut message = message?;
+        message.post(channel.port1())?;
+
+        let acknowledgement = Self::call::<Result<(), String>>(client, Ok(message));
+        let port = channel.port2();
+        spawn_local(async move {
+            let error = match acknowledgement.await {
+                Ok(Ok(())) => return,
+                Ok(Err(error)) => format!("server failed to pipeline result: {error}"),
+                Err(error) => format!("failed to send pipelined call: {error}"),
+            };
+
+            // The result will never be served, so tell the client we handed out as though its
+            // server had
+            if let Err(dispatch_error) = deliver(&port, &Failed::to_js_value(&error)) {
+                log_error!("failed to fail pipelined client after {error}: {dispatch_error:?}");
+            }
+        });
+
+        Ok(T::into_client(channel.port2()))
+    }
+}
+
+impl<P: Port> Client<P> {
+    fn send_deferred(&mut self) {
+        let deferred = std::mem::take(&mut self.deferred_batches);
+        if self
+            .remote
+            .is_some_and(|remote| self.handshake.check(remote).is_ok())
+        {
+            for batch in deferred {
+                self.send_batch(batch);
+            }
+        }
+    }
+
+    fn flush(&mut self) {
+        let batch = std::mem::take(&mut self.batch);
+        self.send_batch(batch);
+    }
+
+    fn send_batch(&self, batch: Batch) {
+        if batch.is_empty() {
+            return;
+        }
+
+        if let Err(error) = batch.send(|message, transfer| {
+            self.port
+                .post_message_with_transfer(message, transfer)
+                .map_err(|error| Error::PostFailed {
+                    error: format!("error posting batch in Client send_batch: {error:?}"),
+                })
+        }) {
+            log_error!("failed to send batch: {error}");
+        }
+    }
+}
+
+/// Reads the error from a message saying that the server is gone or couldn't make a call.
+fn failure(message: &JsValue) -> Option<Error> {
+    Revoked::is_revoked(message)
+        .then_some(Error::Revoked)
+        .or_else(|| Failed::from_js_value(message))
+}
+
+/// Dispatches `message` to the listener on `port` as though it had been posted to it.
+fn deliver(port: &MessagePort, message: &JsValue) -> Result<(), JsValue> {
+    let init = MessageEventInit::new();
+    init.set_data(message);
+    let event = MessageEvent::new_with_event_init_dict("message", &init)?;
+    port.dispatch_event(&event).map(|_| ())
+}
+
+impl<P: Port> Drop for Client<P> {
+    fn drop(&mut self) {
+        // The flush task can't reach us anymore, so send what's pending ourselves before the
+        // server is released. Calls still waiting for the handshake won't hear it now, so they fail
+        self.flush();
+        self.send_deferred();
+        for on_ready in self.on_ready.drain(..) {
+            if let Err(error) = on_ready.call1(&JsValue::NULL, &JsValue::UNDEFINED) {
+                log_error!("failed to fail on_ready callback while dropping client: {error:?}");
+            }
+        }
+
+        if !self.release_on_drop {
+            return;
+        }
+
+        if let Err(error) = self.port.post_message(&Release::to_js_value()) {
+            log_error!("error posting release message: {error:?}");
+        }
+    }
 }
@@ -2,6 +2,9 @@ use thiserror::Error;
 
 #[derive(Debug, Error)]
 pub enum Error {
+    #[error("call failed: {error}")]
+    CallFailed { error: String },
+
     #[error("callback failed")]
     CallbackFailed { error: String },
 
@@ -14,12 +17,26 @@ pub enum Error {
     #[error("failed to deserialize type {type_name}: {error}")]
     DeserializeFailed { type_name: String, error: String },
 
+    #[error("{type_name} is already borrowed")]
+    LocalUnavailable { type_name: String },
+
     #[error("failed to post message: {error}")]
     PostFailed { error: String },
 
+    #[error("protocol mismatch: expected version {expected_version} with signature {expected_signature:016x}, found version {found_version} with signature {found_signature:016x}")]
+    ProtocolMismatch {
+        expected_version: u32,
+        expected_signature: u64,
+        found_version: u32,
+        found_signature: u64,
+    },
+
     #[error("failed to receive message: {error}")]
     ReceiveFailed { error: String },
 
+    #[error("revoked by its owner")]
+    Revoked,
+
     #[error("failed to serialize type {type_name}: {error}")]
     SerializeFailed { type_name: String, error: String },
 
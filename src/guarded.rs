@@ -0,0 +1,238 @@
+use std::any::type_name;
+use std::cell::RefCell;
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::Rc;
+
+use futures::future::Either;
+use futures::FutureExt;
+
+use crate::{Error, Permit, Semaphore};
+
+type BorrowedFuture<'a, T> = Pin<Box<dyn Future<Output = T> + 'a>>;
+type Availability = Rc<dyn Fn(bool) -> bool>;
+
+/// Access weight of a `&mut self` call. `&self` calls weigh 1, so any number of them can share the
+/// value, but an exclusive call waits for all of them.
+const EXCLUSIVE: usize = usize::MAX;
+
+/// The value behind a generated server, along with a queue that hands out access to it in the
+/// order calls arrive.
+///
+/// Procedures returning a `'static` future only need the value while they run synchronously, but
+/// `async fn` procedures hold onto it until their future completes, so later calls wait for them.
+///
+/// Clones share both the value and the queue, so servers created from clones of the same `Guarded`
+/// on different ports wait for each other rather than failing to borrow the value.
+pub struct Guarded<L: ?Sized> {
+    local: Rc<RefCell<L>>,
+    access: Semaphore,
+    /// Whether whatever the value shares can be borrowed, mutably if passed `true`
+    available: Option<Availability>,
+}
+
+impl<L: ?Sized> Clone for Guarded<L> {
+    fn clone(&self) -> Self {
+        Self {
+            local: self.local.clone(),
+            access: self.access.clone(),
+            available: self.available.clone(),
+        }
+    }
+}
+
+impl<L: ?Sized + 'static> From<Rc<RefCell<L>>> for Guarded<L> {
+    fn from(local: Rc<RefCell<L>>) -> Self {
+        Self::new(local)
+    }
+}
+
+#[expect(
+    clippy::future_not_send,
+    reason = "Values are served on the thread that owns them, so calls to them are never sent"
+)]
+impl<L: ?Sized + 'static> Guarded<L> {
+    pub fn new(local: Rc<RefCell<L>>) -> Self {
+        Self {
+            local,
+            access: Semaphore::new(EXCLUSIVE),
+            available: None,
+        }
+    }
+
+    /// For values wrapping something that's also used elsewhere, like proxies wrapping the proxied
+    /// value. Calls check that `shared` can be borrowed before they run, and fail with
+    /// [`Error::LocalUnavailable`] rather than letting the value's borrow of it panic.
+    pub fn sharing<T: ?Sized + 'static>(local: Rc<RefCell<L>>, shared: Rc<RefCell<T>>) -> Self {
+        let available = move |exclusive| {
+            if exclusive {
+                shared.try_borrow_mut().is_ok()
+            } else {
+                shared.try_borrow().is_ok()
+            }
+        };
+
+        Self {
+            available: Some(Rc::new(available)),
+            ..Self::new(local)
+        }
+    }
+
+    /// The value being served, for use alongside the servers.
+    #[must_use]
+    pub const fn local(&self) -> &Rc<RefCell<L>> {
+        &self.local
+    }
+
+    fn unavailable() -> Error {
+        Error::LocalUnavailable {
+            type_name: String::from(type_name::<L>()),
+        }
+    }
+
+    fn check(available: Option<&Availability>, weight: usize) -> Result<(), Error> {
+        match available {
+            Some(available) if !available(weight == EXCLUSIVE) => Err(Self::unavailable()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Queues for a place to run: first a permit from `limiter`, then `weight` units of access to
+    /// the value. Access is only queued for right away if the limiter didn't make us wait, so calls
+    /// held back by a limiter don't hold up calls to other procedures.
+    fn enter(
+        &self,
+        limiter: Option<&Semaphore>,
+        weight: usize,
+    ) -> impl Future<Output = (Option<Permit>, Permit)> {
+        let (limit, access) = match limiter.map(|limiter| limiter.try_acquire(1).ok_or(limiter)) {
+            None => (Ok(None), Some(self.access.acquire(weight))),
+            Some(Ok(permit)) => (Ok(Some(permit)), Some(self.access.acquire(weight))),
+            Some(Err(limiter)) => (Err(limiter.acquire(1)), None),
+        };
+
+        let queue = self.access.clone();
+        async move {
+            let limit = match limit {
+                Ok(limit) => limit,
+                Err(acquire) => Some(acquire.await),
+            };
+            let access = access.unwrap_or_else(|| queue.acquire(weight)).await;
+            (limit, access)
+        }
+    }
+
+    /// Runs a `&mut self` procedure whose future doesn't borrow the value, holding a permit from
+    /// `limiter` until the future completes. If nothing is queued ahead of it, it runs immediately.
+    ///
+    /// # Errors
+    ///
+    /// Resolves to [`Error::LocalUnavailable`] if the value can't be borrowed when the call runs.
+    pub fn call<T: 'static>(
+        &self,
+        limiter: Option<&Semaphore>,
+        procedure: impl FnOnce(&mut L) -> Box<dyn Future<Output = T>> + 'static,
+    ) -> impl Future<Output = Result<T, Error>> {
+        self.start(limiter, EXCLUSIVE, move |local| {
+            let mut local = local.try_borrow_mut().map_err(|_| Self::unavailable())?;
+            Ok(procedure(&mut local))
+        })
+    }
+
+    /// Runs a `&self` procedure whose future doesn't borrow the value, like [`Guarded::call`].
+    ///
+    /// # Errors
+    ///
+    /// Resolves to [`Error::LocalUnavailable`] if the value can't be borrowed when the call runs.
+    pub fn call_shared<T: 'static>(
+        &self,
+        limiter: Option<&Semaphore>,
+        procedure: impl FnOnce(&L) -> Box<dyn Future<Output = T>> + 'static,
+    ) -> impl Future<Output = Result<T, Error>> {
+        self.start(limiter, 1, move |local| {
+            let local = local.try_borrow().map_err(|_| Self::unavailable())?;
+            Ok(procedure(&local))
+        })
+    }
+
+    fn start<T: 'static>(
+        &self,
+        limiter: Option<&Semaphore>,
+        weight: usize,
+        procedure: impl FnOnce(&RefCell<L>) -> Result<Box<dyn Future<Output = T>>, Error> + 'static,
+    ) -> impl Future<Output = Result<T, Error>> {
+        let mut entry = Box::pin(self.enter(limiter, weight));
+        if let Some((limit, access)) = (&mut entry).now_or_never() {
+            let future =
+                Self::check(self.available.as_ref(), weight).and_then(|()| procedure(&self.local));
+            drop(access);
+
+            return Either::Left(async move {
+                let _limit = limit;
+                Ok(Box::into_pin(future?).await)
+            });
+        }
+
+        let local = self.local.clone();
+        let available = self.available.clone();
+        Either::Right(async move {
+            let (_limit, access) = entry.await;
+            Self::check(available.as_ref(), weight)?;
+            let future = procedure(&local)?;
+            drop(access);
+            Ok(Box::into_pin(future).await)
+        })
+    }
+
+    /// Runs a `&mut self` procedure whose future borrows the value, such as an `async fn`, holding
+    /// onto the value and a permit from `limiter` until the future completes.
+    ///
+    /// # Errors
+    ///
+    /// Resolves to [`Error::LocalUnavailable`] if the value can't be borrowed when the call runs.
+    #[expect(
+        clippy::await_holding_refcell_ref,
+        reason = "The access queue keeps other calls from borrowing the value until we're done"
+    )]
+    pub fn call_borrowing<T: 'static>(
+        &self,
+        limiter: Option<&Semaphore>,
+        procedure: impl for<'a> FnOnce(&'a mut L) -> BorrowedFuture<'a, T> + 'static,
+    ) -> impl Future<Output = Result<T, Error>> {
+        let local = self.local.clone();
+        let available = self.available.clone();
+        let entry = self.enter(limiter, EXCLUSIVE);
+        async move {
+            let (_limit, _access) = entry.await;
+            Self::check(available.as_ref(), EXCLUSIVE)?;
+            let mut local = local.try_borrow_mut().map_err(|_| Self::unavailable())?;
+            Ok(procedure(&mut local).await)
+        }
+    }
+
+    /// Runs a `&self` procedure whose future borrows the value. Other `&self` calls can run while
+    /// it's waiting, but `&mut self` calls wait for it to complete.
+    ///
+    /// # Errors
+    ///
+    /// Resolves to [`Error::LocalUnavailable`] if the value can't be borrowed when the call runs.
+    #[expect(
+        clippy::await_holding_refcell_ref,
+        reason = "The access queue keeps other calls from mutably borrowing the value until we're done"
+    )]
+    pub fn call_shared_borrowing<T: 'static>(
+        &self,
+        limiter: Option<&Semaphore>,
+        procedure: impl for<'a> FnOnce(&'a L) -> BorrowedFuture<'a, T> + 'static,
+    ) -> impl Future<Output = Result<T, Error>> {
+        let local = self.local.clone();
+        let available = self.available.clone();
+        let entry = self.enter(limiter, 1);
+        async move {
+            let (_limit, _access) = entry.await;
+            Self::check(available.as_ref(), 1)?;
+            let local = local.try_borrow().map_err(|_| Self::unavailable())?;
+            Ok(procedure(&local).await)
+        }
+    }
+}
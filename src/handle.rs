@@ -1,40 +1,115 @@
 use std::any::type_name;
+use std::cell::RefCell;
+use std::rc::{Rc, Weak};
 
 use js_sys::Array;
-use wasm_bindgen::{JsCast, JsValue};
-use web_sys::{MessageChannel, MessagePort};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{MessageChannel, MessageEvent, MessagePort};
 
-use crate::{Error, Post, Transfer};
+use crate::message::{Release, Revoked};
+use crate::{log_error, Error, Notifier, Post, Revoker, Transfer};
 
-pub trait AsHandle<T> {
+pub trait AsHandle<T: ?Sized> {
     type Client;
     type Server;
     fn into_client(port: MessagePort) -> Self::Client;
-    fn create_server(local: T, port: MessagePort);
+    fn create_server(local: Rc<RefCell<T>>, port: MessagePort) -> Revoker;
+
+    /// Serves `local` like [`AsHandle::create_server`], checking the watchers of its fields
+    /// whenever `notifier` is notified. Only proxies have watchers.
+    fn create_notified_server(
+        local: Rc<RefCell<T>>,
+        port: MessagePort,
+        notifier: &Notifier,
+    ) -> Revoker {
+        let _ = notifier;
+        Self::create_server(local, port)
+    }
 }
 
-pub struct Handle<T: AsHandle<T>> {
-    local: Option<T>,
+/// A value passed to a remote, which receives a client served from this side of the call.
+///
+/// Handles created from an `Rc<RefCell<T>>` serve the value without taking it, so it can still be
+/// used locally and handed out again in other handles.
+pub struct Handle<T: AsHandle<T> + ?Sized> {
+    local: Option<Rc<RefCell<T>>>,
     remote: Option<MessagePort>,
+    revoker: Option<Revoker>,
+    notifier: Option<Notifier>,
 }
 
 impl<T: AsHandle<T>> From<T> for Handle<T> {
     fn from(local: T) -> Self {
+        Self::new_shared(Rc::new(RefCell::new(local)))
+    }
+}
+
+impl<T: AsHandle<T> + ?Sized> From<Rc<RefCell<T>>> for Handle<T> {
+    fn from(local: Rc<RefCell<T>>) -> Self {
+        Self::new_shared(local)
+    }
+}
+
+impl<T: AsHandle<T> + ?Sized> Handle<T> {
+    /// Serves a value that's also used elsewhere. Remote calls borrow it while they run, and fail
+    /// with [`Error::LocalUnavailable`](crate::Error::LocalUnavailable) if it's already borrowed,
+    /// so don't hold onto a borrow of it across an `await`.
+    pub const fn new_shared(local: Rc<RefCell<T>>) -> Self {
         Self {
             local: Some(local),
             remote: None,
+            revoker: None,
+            notifier: None,
         }
     }
-}
 
-impl<T: AsHandle<T>> Handle<T> {
     pub fn new_remote(port: MessagePort) -> Self {
         Self {
             local: None,
             remote: Some(port),
+            revoker: None,
+            notifier: None,
         }
     }
 
+    /// Lets `revoker` stop serving the value once this is sent. Handles received from a remote
+    /// can only be revoked by whoever serves them.
+    pub fn revocable_by(&mut self, revoker: &Revoker) {
+        self.revoker = Some(revoker.clone());
+    }
+
+    /// Lets `notifier` tell watchers of the value's fields that it changed once this is sent, for
+    /// proxied values that change other than through remote calls.
+    pub fn notified_by(&mut self, notifier: &Notifier) {
+        self.notifier = Some(notifier.clone());
+    }
+
+    /// Serves the local value on `port`, the counterpart of whichever port the remote holds.
+    /// Handles received from a remote relay what arrives on `port` to the value's server instead.
+    fn serve(self, port: MessagePort) -> Result<(), Error> {
+        let Some(local) = self.local else {
+            let Some(remote) = self.remote else {
+                return Err(Error::SerializeFailed {
+                    type_name: String::from(type_name::<T>()),
+                    error: String::from("handle has neither a local value nor a remote port"),
+                });
+            };
+
+            Relay::create(port, remote);
+            return Ok(());
+        };
+
+        let served = match &self.notifier {
+            Some(notifier) => T::create_notified_server(local, port, notifier),
+            None => T::create_server(local, port),
+        };
+        if let Some(revoker) = self.revoker {
+            revoker.on_revoke(move || served.revoke());
+        }
+        Ok(())
+    }
+
     pub fn try_into_client(self) -> Result<T::Client, Error> {
         self.remote
             .ok_or_else(|| Error::CreationFailed {
@@ -45,7 +120,7 @@ impl<T: AsHandle<T>> Handle<T> {
     }
 }
 
-impl<T: AsHandle<T>> Post for Handle<T> {
+impl<T: AsHandle<T> + ?Sized> Post for Handle<T> {
     const POSTABLE: bool = true;
 
     fn from_js_value(value: JsValue) -> Result<Self, Error> {
@@ -57,25 +132,123 @@ impl<T: AsHandle<T>> Post for Handle<T> {
     }
 
     fn to_js_value(self) -> Result<JsValue, Error> {
-        let Some(local) = self.local else {
-            return Err(Error::SerializeFailed {
-                type_name: String::from(type_name::<T>()),
-                error: String::from("local not set when trying to send"),
-            });
-        };
+        // Handles received from a remote are passed on by transferring their port, so the new
+        // recipient talks to the value's server directly
+        if self.local.is_none() {
+            if let Some(remote) = self.remote {
+                return Post::to_js_value(remote);
+            }
+        }
 
         let channel = MessageChannel::new().map_err(|error| Error::CreationFailed {
             type_name: String::from("MessageChannel"),
             error: format!("failed to create MessageChannel in Handle::to_js_value: {error:?}"),
         })?;
 
-        T::create_server(local, channel.port1());
+        self.serve(channel.port1())?;
         Post::to_js_value(channel.port2())
     }
 }
 
-impl<T: AsHandle<T>> Transfer for Handle<T> {
+impl<T: AsHandle<T> + ?Sized> Transfer for Handle<T> {
     fn get_transferable(js_value: &JsValue) -> Option<Array> {
         Some(Array::of1(js_value))
     }
 }
+
+/// Results that can be served on a port supplied by the caller, which lets the caller start using
+/// the result before the call that produces it has completed.
+pub trait Pipeline: Sized {
+    /// Serves the result on `port`.
+    ///
+    /// # Errors
+    ///
+    /// Fails for results that can't be served, which is anything but a [`Handle`].
+    fn pipeline(self, port: MessagePort) -> Result<(), Error>;
+}
+
+impl<T> Pipeline for T {
+    default fn pipeline(self, _port: MessagePort) -> Result<(), Error> {
+        Err(Error::UnsupportedType {
+            name: String::from(type_name::<T>()),
+        })
+    }
+}
+
+impl<T: AsHandle<T> + ?Sized> Pipeline for Handle<T> {
+    fn pipeline(self, port: MessagePort) -> Result<(), Error> {
+        self.serve(port)
+    }
+}
+
+/// Passes messages both ways between the port a pipelined result is served on and the port of a
+/// handle received from a remote, since the caller already holds the counterpart of the former.
+struct Relay {
+    /// The relay holds a reference to itself so it can keep its closures alive.
+    /// Once the client releases the value or its server revokes it, it lets go of this reference.
+    phylactery: Option<Rc<RefCell<Self>>>,
+    ports: [MessagePort; 2],
+    #[expect(
+        dead_code,
+        reason = "We hold onto these closures' memory until the relay is dropped"
+    )]
+    on_message: [Closure<dyn Fn(MessageEvent)>; 2],
+}
+
+impl Relay {
+    fn create(local: MessagePort, remote: MessagePort) {
+        let relay = Rc::new_cyclic(|weak_self: &Weak<RefCell<Self>>| {
+            let on_message = [
+                Self::relaying(weak_self, remote.clone()),
+                Self::relaying(weak_self, local.clone()),
+            ];
+            local.set_onmessage(Some(on_message[0].as_ref().unchecked_ref()));
+            remote.set_onmessage(Some(on_message[1].as_ref().unchecked_ref()));
+
+            RefCell::new(Self {
+                phylactery: None,
+                ports: [local, remote],
+                on_message,
+            })
+        });
+
+        relay.borrow_mut().phylactery = Some(relay.clone());
+    }
+
+    /// Posts each message on to `to`, along with the ports it transfers.
+    fn relaying(weak_self: &Weak<RefCell<Self>>, to: MessagePort) -> Closure<dyn Fn(MessageEvent)> {
+        let cloned_weak_self = weak_self.clone();
+        Closure::new(move |event: MessageEvent| {
+            let data = event.data();
+            if let Err(error) = to.post_message_with_transferable(&data, &event.ports()) {
+                log_error!("error relaying message to forwarded handle: {error:?}");
+            }
+
+            let released = Array::is_array(&data) && Release::is_release(data.unchecked_ref());
+            if !released && !Revoked::is_revoked(&data) {
+                return;
+            }
+
+            let Some(relay) = Weak::upgrade(&cloned_weak_self) else {
+                log_error!("failed to upgrade weak relay in message callback");
+                return;
+            };
+
+            let Ok(mut relay) = relay.try_borrow_mut() else {
+                log_error!("failed to borrow relay to release it");
+                return;
+            };
+
+            relay.release();
+        })
+    }
+
+    /// Stops listening and lets go of the relay.
+    fn release(&mut self) {
+        for port in &self.ports {
+            port.set_onmessage(None);
+            port.close();
+        }
+        self.phylactery = None;
+    }
+}
@@ -0,0 +1,98 @@
+use std::any::type_name;
+
+use js_sys::Array;
+use wasm_bindgen::prelude::*;
+
+use crate::{Error, Post};
+
+/// Bumped whenever the wire format changes in a way that older peers can't understand.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+const HANDSHAKE: &str = "*handshake";
+
+/// Folds a supertrait's signature into a trait's own, so that a client and server only match if
+/// they agree on the whole trait hierarchy.
+#[must_use]
+pub const fn extend_signature(signature: u64, supertrait: u64) -> u64 {
+    (signature ^ supertrait.rotate_left(1)).wrapping_mul(0x0100_0000_01b3)
+}
+
+/// Folds the name of a type a generic trait is instantiated with into the trait's signature, so that
+/// a client and server only match if they agree on the instantiation too.
+#[must_use]
+pub fn instantiate_signature<T: ?Sized>(signature: u64) -> u64 {
+    let name = type_name::<T>()
+        .bytes()
+        .fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+        });
+    extend_signature(signature, name)
+}
+
+/// The first message exchanged in each direction, identifying the wire protocol and the trait being
+/// served so that stale workers are detected instead of silently misbehaving.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Handshake {
+    version: u32,
+    signature: u64,
+}
+
+impl Handshake {
+    pub const fn new(signature: u64) -> Self {
+        Self {
+            version: PROTOCOL_VERSION,
+            signature,
+        }
+    }
+
+    pub fn to_js_value(self) -> JsValue {
+        // The signature is sent as a string because it doesn't fit in a JavaScript number
+        Array::of3(
+            &JsValue::from_str(HANDSHAKE),
+            &JsValue::from(self.version),
+            &JsValue::from_str(&format!("{:016x}", self.signature)),
+        )
+        .into()
+    }
+
+    /// Returns `None` if `value` isn't a handshake at all.
+    pub fn from_js_value(value: &JsValue) -> Option<Self> {
+        // Peers predating protocol versioning send a bare string
+        if value.as_string().is_some_and(|value| value == HANDSHAKE) {
+            return Some(Self {
+                version: 0,
+                signature: 0,
+            });
+        }
+
+        let array = value.dyn_ref::<Array>()?;
+        if array.get(0).as_string()? != HANDSHAKE {
+            return None;
+        }
+
+        // Version 0 clients send a one-element array
+        if array.length() == 1 {
+            return Some(Self {
+                version: 0,
+                signature: 0,
+            });
+        }
+
+        let version = u32::from_js_value(array.get(1)).ok()?;
+        let signature = u64::from_str_radix(&array.get(2).as_string()?, 16).ok()?;
+        Some(Self { version, signature })
+    }
+
+    pub const fn check(self, remote: Self) -> Result<(), Error> {
+        if self.version == remote.version && self.signature == remote.signature {
+            Ok(())
+        } else {
+            Err(Error::ProtocolMismatch {
+                expected_version: self.version,
+                expected_signature: self.signature,
+                found_version: remote.version,
+                found_signature: remote.signature,
+            })
+        }
+    }
+}
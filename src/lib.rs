@@ -4,25 +4,49 @@
 
 extern crate combadge_macros;
 
+// Items only generated code needs are exported but left out of the docs
+
 mod callback;
 pub use callback::Callback;
 mod client;
 pub use client::Client;
 mod error;
 pub use error::Error;
+mod guarded;
+#[doc(hidden)]
+pub use guarded::Guarded;
 mod handle;
-pub use handle::{AsHandle, Handle};
+pub use handle::{AsHandle, Handle, Pipeline};
+mod handshake;
+#[doc(hidden)]
+pub use handshake::{extend_signature, instantiate_signature};
+mod local;
+pub use local::Local;
 mod log;
 mod message;
 pub use message::Message;
+#[doc(hidden)]
+pub use message::Procedure;
 mod port;
 pub use port::Port;
 mod post;
 pub use post::{Post, Transfer};
+mod revoker;
+pub use revoker::Revoker;
+mod semaphore;
+pub use semaphore::Concurrency;
+#[doc(hidden)]
+pub use semaphore::{Acquire, Permit, Semaphore};
 mod server;
 pub use server::Server;
+#[doc(hidden)]
+pub use server::{fail, respond};
 mod maybe_async;
 pub use maybe_async::MaybeAsync;
+mod watchers;
+pub use watchers::Notifier;
+#[doc(hidden)]
+pub use watchers::Watchers;
 
 pub mod reexports {
     pub use ::futures;
@@ -0,0 +1,10 @@
+/// A local implementation of a `#[combadge]` trait, used through the trait's async mirror
+/// (`AsyncFoo` for `Foo`) so that the same code can run with or without a worker.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Local<L>(pub L);
+
+impl<L> Local<L> {
+    pub fn into_inner(self) -> L {
+        self.0
+    }
+}
@@ -1,4 +1,5 @@
 use std::future::Future;
+use std::pin::Pin;
 
 use futures::future::ready;
 
@@ -17,3 +18,9 @@ impl<T: Sized + 'static> MaybeAsync<T> for Box<dyn Future<Output = T>> {
         self
     }
 }
+
+impl<T: Sized + 'static> MaybeAsync<T> for Pin<Box<dyn Future<Output = T>>> {
+    fn to_maybe_async(self) -> Box<dyn Future<Output = T>> {
+        Box::new(self)
+    }
+}
@@ -1,3 +1,5 @@
+use std::fmt;
+
 use combadge_macros::build_post_tuple;
 use js_sys::Array;
 use wasm_bindgen::prelude::*;
@@ -9,6 +11,67 @@ use wasm_bindgen::convert::RefFromWasmAbi;
 
 use crate::{Error, Post, Transfer};
 
+const SUPERTRAIT: &str = "*super";
+
+/// Identifies the procedure a message invokes.
+///
+/// Generated clients send the compact numeric ID assigned by `#[combadge]`, or the procedure's
+/// name when the `procedure_names` feature is enabled to make messages readable while debugging.
+/// Generated servers accept either.
+///
+/// Calls to a supertrait's procedures are prefixed with the supertrait's index, and are passed on
+/// to the supertrait's dispatcher.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Procedure {
+    Id(u32),
+    Name(String),
+}
+
+impl Procedure {
+    /// Reads the procedure a message invokes.
+    ///
+    /// # Errors
+    ///
+    /// Fails if `value` is neither a procedure name nor an ID.
+    pub fn from_js_value(value: JsValue) -> Result<Self, Error> {
+        if let Some(name) = value.as_string() {
+            return Ok(Self::Name(name));
+        }
+
+        u32::from_js_value(value)
+            .map(Self::Id)
+            .map_err(|error| Error::DeserializeFailed {
+                type_name: String::from("Procedure"),
+                error: format!("expected a procedure name or ID: {error}"),
+            })
+    }
+
+    /// If this procedure routes to a supertrait, reads the supertrait's index and the procedure to
+    /// invoke on it from the front of `data`.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the index or the procedure can't be read.
+    pub fn supertrait(&self, data: &Array) -> Result<Option<(u32, Self)>, Error> {
+        if !matches!(self, Self::Name(name) if name == SUPERTRAIT) {
+            return Ok(None);
+        }
+
+        let index = u32::from_js_value(data.shift())?;
+        let procedure = Self::from_js_value(data.shift())?;
+        Ok(Some((index, procedure)))
+    }
+}
+
+impl fmt::Display for Procedure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Id(id) => write!(f, "#{id}"),
+            Self::Name(name) => f.write_str(name),
+        }
+    }
+}
+
 pub struct Message {
     message: Vec<JsValue>,
     transfer: Vec<JsValue>,
@@ -22,6 +85,28 @@ impl Message {
         }
     }
 
+    /// Starts a message invoking the procedure generated for a `#[combadge]` trait method. `route`
+    /// lists the supertrait indices leading from the served trait to the method's trait.
+    #[must_use]
+    pub fn new_procedure(route: &[u32], id: u32, name: &str) -> Self {
+        let procedure = if cfg!(feature = "procedure_names") {
+            JsValue::from_str(name)
+        } else {
+            JsValue::from(id)
+        };
+
+        let message = route
+            .iter()
+            .flat_map(|index| [JsValue::from_str(SUPERTRAIT), JsValue::from(*index)])
+            .chain([procedure])
+            .collect();
+
+        Self {
+            message,
+            transfer: Vec::new(),
+        }
+    }
+
     pub fn post<T>(&mut self, message: T) -> Result<(), Error>
     where
         T: Post + Transfer,
@@ -46,6 +131,141 @@ impl Message {
     }
 }
 
+const BATCH: &str = "*batch";
+
+/// Several messages coalesced into a single `postMessage` so that they share one structured clone.
+#[derive(Debug, Default)]
+pub struct Batch {
+    messages: Vec<JsValue>,
+    transfer: Vec<JsValue>,
+    stop_on_error: bool,
+}
+
+impl Batch {
+    /// Asks the server to skip the rest of the batch once a call fails to dispatch, answering the
+    /// skipped calls with an error. Only calls that never reach their procedure count as failures,
+    /// like those whose arguments fail to deserialize. A procedure returning an error doesn't stop
+    /// the batch, since the server doesn't look at the results it sends back.
+    pub const fn stop_on_error(&mut self) {
+        self.stop_on_error = true;
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.messages.is_empty()
+    }
+
+    pub fn push(&mut self, message: &JsValue, transfer: &JsValue) {
+        self.messages.push(message.clone());
+        self.transfer
+            .extend(transfer.unchecked_ref::<Array>().iter());
+    }
+
+    pub fn send<T>(self, sender: T) -> Result<(), Error>
+    where
+        T: FnOnce(&JsValue, &JsValue) -> Result<(), Error>,
+    {
+        let message = [
+            JsValue::from_str(BATCH),
+            JsValue::from_bool(self.stop_on_error),
+        ]
+        .into_iter()
+        .chain(self.messages)
+        .collect::<Array>();
+        let transfer = self.transfer.into_iter().collect::<Array>();
+        sender(message.as_ref(), transfer.as_ref())
+    }
+
+    /// Splits a received batch back into its messages and whether to stop at the first failure,
+    /// or returns `None` if `data` is a single message.
+    pub fn unbatch(data: &Array) -> Option<(Vec<Array>, bool)> {
+        if data
+            .get(0)
+            .as_string()
+            .is_none_or(|procedure| procedure != BATCH)
+        {
+            return None;
+        }
+
+        let stop_on_error = data.get(1).is_truthy();
+        let messages = data.iter().skip(2).map(JsValue::unchecked_into).collect();
+        Some((messages, stop_on_error))
+    }
+}
+
+const RELEASE: &str = "*release";
+
+/// Sent by a client that owns the value its server serves once the client is dropped, so that the
+/// server can drop the value and stop listening.
+pub struct Release;
+
+impl Release {
+    pub fn to_js_value() -> JsValue {
+        Array::of1(&JsValue::from_str(RELEASE)).into()
+    }
+
+    pub fn is_release(data: &Array) -> bool {
+        data.length() == 1
+            && data
+                .get(0)
+                .as_string()
+                .is_some_and(|procedure| procedure == RELEASE)
+    }
+}
+
+const REVOKED: &str = "*revoked";
+
+/// Sent by a server whose value was revoked, just before it stops listening, so that the client
+/// fails the calls it's waiting on.
+pub struct Revoked;
+
+impl Revoked {
+    pub fn to_js_value() -> JsValue {
+        Array::of1(&JsValue::from_str(REVOKED)).into()
+    }
+
+    pub fn is_revoked(data: &JsValue) -> bool {
+        Array::is_array(data) && {
+            let data: &Array = data.unchecked_ref();
+            data.length() == 1
+                && data
+                    .get(0)
+                    .as_string()
+                    .is_some_and(|procedure| procedure == REVOKED)
+        }
+    }
+}
+
+const FAILED: &str = "*failed";
+
+/// Sent by a server in answer to a call that it couldn't make, so that the caller fails instead of
+/// waiting for a result that will never come.
+pub struct Failed;
+
+impl Failed {
+    pub fn to_js_value(error: &str) -> JsValue {
+        Array::of2(&JsValue::from_str(FAILED), &JsValue::from_str(error)).into()
+    }
+
+    pub fn from_js_value(data: &JsValue) -> Option<Error> {
+        if !Array::is_array(data) {
+            return None;
+        }
+
+        let data: &Array = data.unchecked_ref();
+        if data.length() != 2
+            || data
+                .get(0)
+                .as_string()
+                .is_none_or(|procedure| procedure != FAILED)
+        {
+            return None;
+        }
+
+        let error = data.get(1).as_string()?;
+        Some(Error::CallFailed { error })
+    }
+}
+
 pub(crate) trait PostTuple<T> {
     fn post_tuple(&mut self, tuple: T) -> Result<(), Error>;
 }
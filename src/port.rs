@@ -10,6 +10,10 @@ pub trait Port {
         message: &JsValue,
         transfer: &JsValue,
     ) -> Result<(), JsValue>;
+
+    /// Disconnects the port once nothing will be sent on it again. Ports that outlive their
+    /// servers, like a worker's global scope, are left open.
+    fn close(&self) {}
 }
 
 impl Port for DedicatedWorkerGlobalScope {
@@ -46,6 +50,10 @@ impl Port for MessagePort {
     ) -> Result<(), JsValue> {
         self.post_message_with_transferable(message, transfer)
     }
+
+    fn close(&self) {
+        self.close()
+    }
 }
 
 impl Port for Worker {
@@ -0,0 +1,77 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::time::Duration;
+
+use wasm_bindgen::prelude::*;
+
+use crate::log_error;
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_name = setTimeout)]
+    fn set_timeout(handler: &JsValue, timeout: i32);
+}
+
+#[derive(Default)]
+struct State {
+    revoked: bool,
+    on_revoke: Vec<Box<dyn FnOnce()>>,
+}
+
+/// Lets the owner of values handed out in [`Handle`](crate::Handle)s and
+/// [`Callback`](crate::Callback)s take them back.
+///
+/// Revoking tears down the servers of the values, dropping them, and calls from remotes fail with
+/// [`Error::Revoked`](crate::Error::Revoked), including those already on their way. The servers are
+/// torn down once the current task finishes, so values can revoke themselves from their own
+/// methods. Clones revoke the same values, so one revoker can cover everything handed to a remote.
+#[derive(Clone, Default)]
+pub struct Revoker {
+    state: Rc<RefCell<State>>,
+}
+
+impl Revoker {
+    pub fn revoke(&self) {
+        // Take the callbacks out first so that they're free to use the revoker
+        let on_revoke = {
+            let Ok(mut state) = self.state.try_borrow_mut() else {
+                log_error!("failed to borrow revoker to revoke");
+                return;
+            };
+
+            state.revoked = true;
+            std::mem::take(&mut state.on_revoke)
+        };
+
+        for on_revoke in on_revoke {
+            on_revoke();
+        }
+    }
+
+    /// Revokes once `timeout` has passed, for values lent out for a limited time.
+    pub fn revoke_after(&self, timeout: Duration) {
+        let revoker = self.clone();
+        let timeout = i32::try_from(timeout.as_millis()).unwrap_or(i32::MAX);
+        set_timeout(&Closure::once_into_js(move || revoker.revoke()), timeout);
+    }
+
+    #[must_use]
+    pub fn is_revoked(&self) -> bool {
+        self.state.borrow().revoked
+    }
+
+    /// Runs `on_revoke` when this is revoked, or right away if it already has been.
+    pub fn on_revoke(&self, on_revoke: impl FnOnce() + 'static) {
+        let Ok(mut state) = self.state.try_borrow_mut() else {
+            log_error!("failed to borrow revoker to add a revocation");
+            return;
+        };
+
+        if state.revoked {
+            drop(state);
+            on_revoke();
+        } else {
+            state.on_revoke.push(Box::new(on_revoke));
+        }
+    }
+}
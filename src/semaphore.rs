@@ -0,0 +1,244 @@
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+
+use futures::channel::oneshot;
+use futures::FutureExt;
+
+struct State {
+    available: usize,
+    waiters: VecDeque<(usize, oneshot::Sender<()>)>,
+}
+
+impl State {
+    fn release(&mut self, count: usize) {
+        self.available += count;
+        while let Some((count, _)) = self.waiters.front() {
+            if *count > self.available {
+                break;
+            }
+
+            let Some((count, waiter)) = self.waiters.pop_front() else {
+                break;
+            };
+
+            // Permits handed to a waiter that has gone away are returned to the pool
+            if waiter.send(()).is_ok() {
+                self.available -= count;
+            }
+        }
+    }
+}
+
+/// A single-threaded semaphore that grants permits strictly in the order they were requested, so
+/// that calls queued on it run in arrival order.
+#[derive(Clone)]
+pub struct Semaphore {
+    state: Rc<RefCell<State>>,
+}
+
+impl Semaphore {
+    #[must_use]
+    pub fn new(permits: usize) -> Self {
+        Self {
+            state: Rc::new(RefCell::new(State {
+                available: permits,
+                waiters: VecDeque::new(),
+            })),
+        }
+    }
+
+    /// Takes `count` permits if they're available and nobody is already waiting for permits.
+    #[must_use]
+    pub fn try_acquire(&self, count: usize) -> Option<Permit> {
+        let mut state = self.state.borrow_mut();
+        if !state.waiters.is_empty() || state.available < count {
+            return None;
+        }
+
+        state.available -= count;
+        Some(Permit {
+            state: self.state.clone(),
+            count,
+        })
+    }
+
+    /// Takes `count` permits once every earlier request has been granted and enough permits are
+    /// free. The request joins the queue immediately rather than when the future is first polled.
+    #[must_use]
+    pub fn acquire(&self, count: usize) -> Acquire {
+        if let Some(permit) = self.try_acquire(count) {
+            return Acquire {
+                state: self.state.clone(),
+                count,
+                permit: Some(permit),
+                receiver: None,
+            };
+        }
+
+        let (sender, receiver) = oneshot::channel();
+        self.state.borrow_mut().waiters.push_back((count, sender));
+        Acquire {
+            state: self.state.clone(),
+            count,
+            permit: None,
+            receiver: Some(receiver),
+        }
+    }
+}
+
+/// Permits taken from a [`Semaphore`], returned when dropped.
+pub struct Permit {
+    state: Rc<RefCell<State>>,
+    count: usize,
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        self.state.borrow_mut().release(self.count);
+    }
+}
+
+pub struct Acquire {
+    state: Rc<RefCell<State>>,
+    count: usize,
+    permit: Option<Permit>,
+    receiver: Option<oneshot::Receiver<()>>,
+}
+
+impl Future for Acquire {
+    type Output = Permit;
+
+    fn poll(mut self: Pin<&mut Self>, context: &mut Context<'_>) -> Poll<Permit> {
+        if let Some(receiver) = &mut self.receiver {
+            // The sender is only dropped after sending, so cancellation can't happen
+            if receiver.poll_unpin(context).is_pending() {
+                return Poll::Pending;
+            }
+
+            self.receiver = None;
+            self.permit = Some(Permit {
+                state: self.state.clone(),
+                count: self.count,
+            });
+        }
+
+        self.permit.take().map_or(Poll::Pending, Poll::Ready)
+    }
+}
+
+impl Drop for Acquire {
+    fn drop(&mut self) {
+        // If we were granted permits but never handed them out, give them back
+        if let Some(mut receiver) = self.receiver.take() {
+            receiver.close();
+            if receiver.try_recv() == Ok(Some(())) {
+                self.state.borrow_mut().release(self.count);
+            }
+        }
+    }
+}
+
+/// How many calls to a procedure a generated server runs at once. A call counts as running from
+/// when it starts until the future it returns completes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Concurrency {
+    /// Each call waits for the previous one to complete.
+    Serial,
+    /// Up to this many calls run at once, and the rest wait in arrival order.
+    Bounded(usize),
+    /// Calls start as soon as they arrive, so async calls interleave.
+    #[default]
+    Concurrent,
+}
+
+impl Concurrency {
+    /// Returns the semaphore enforcing this policy, if it needs one.
+    #[must_use]
+    pub fn limiter(self) -> Option<Semaphore> {
+        match self {
+            Self::Serial => Some(Semaphore::new(1)),
+            Self::Bounded(permits) => Some(Semaphore::new(permits)),
+            Self::Concurrent => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::FutureExt;
+
+    use super::*;
+
+    #[test]
+    fn grants_permits_in_request_order() {
+        let semaphore = Semaphore::new(1);
+        let held = semaphore.try_acquire(1).expect("permit should be free");
+        let mut first = semaphore.acquire(1);
+        let mut second = semaphore.acquire(1);
+        assert!((&mut first).now_or_never().is_none());
+
+        drop(held);
+        assert!(semaphore.try_acquire(1).is_none());
+        assert!((&mut second).now_or_never().is_none());
+        let first = first
+            .now_or_never()
+            .expect("first request should be granted");
+
+        drop(first);
+        assert!(second.now_or_never().is_some());
+    }
+
+    #[test]
+    fn exclusive_request_waits_for_every_permit_and_blocks_later_requests() {
+        let semaphore = Semaphore::new(3);
+        let shared = semaphore
+            .acquire(1)
+            .now_or_never()
+            .expect("permit should be free");
+        let mut exclusive = semaphore.acquire(3);
+        let mut later = semaphore.acquire(1);
+        assert!((&mut exclusive).now_or_never().is_none());
+        assert!((&mut later).now_or_never().is_none());
+
+        drop(shared);
+        let exclusive = exclusive
+            .now_or_never()
+            .expect("exclusive request should be granted");
+        assert!((&mut later).now_or_never().is_none());
+
+        drop(exclusive);
+        assert!(later.now_or_never().is_some());
+    }
+
+    #[test]
+    fn dropped_request_returns_granted_permits() {
+        let semaphore = Semaphore::new(1);
+        let held = semaphore.try_acquire(1).expect("permit should be free");
+        let granted = semaphore.acquire(1);
+        let mut next = semaphore.acquire(1);
+
+        drop(held);
+        assert!((&mut next).now_or_never().is_none());
+
+        drop(granted);
+        assert!(next.now_or_never().is_some());
+    }
+
+    #[test]
+    fn dropped_request_is_skipped() {
+        let semaphore = Semaphore::new(1);
+        let held = semaphore.try_acquire(1).expect("permit should be free");
+        let abandoned = semaphore.acquire(1);
+        let mut next = semaphore.acquire(1);
+
+        drop(abandoned);
+        assert!((&mut next).now_or_never().is_none());
+
+        drop(held);
+        assert!(next.now_or_never().is_some());
+    }
+}
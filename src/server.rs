@@ -1,13 +1,18 @@
 use std::cell::RefCell;
 use std::rc::{Rc, Weak};
 
+use std::any::type_name;
+
 use js_sys::Array;
 use wasm_bindgen::prelude::*;
-use web_sys::MessageEvent;
+use wasm_bindgen_futures::spawn_local;
+use web_sys::{MessageEvent, MessagePort};
 
-use crate::{log_error, Error, Port};
+use crate::handshake::Handshake;
+use crate::message::{Batch, Failed, Release, Revoked};
+use crate::{log_error, Error, Pipeline, Port, Post, Procedure, Revoker, Transfer};
 
-type Dispatcher = Box<dyn FnMut(&str, Array) -> Result<(), Error>>;
+type Dispatcher = Box<dyn FnMut(&Procedure, Array) -> Result<(), Error>>;
 
 pub struct Server<P: Port> {
     phylactery: Option<Rc<RefCell<Self>>>,
@@ -21,7 +26,10 @@ pub struct Server<P: Port> {
 }
 
 impl<P: Port + 'static> Server<P> {
-    pub fn create(port: P, dispatcher: Dispatcher) {
+    /// Serves `dispatcher` on `port`, handshaking with clients of a trait with the given signature.
+    /// Returns a revoker that stops serving it.
+    pub fn create(port: P, signature: u64, dispatcher: Dispatcher) -> Revoker {
+        let handshake = Handshake::new(signature);
         let server = Rc::new_cyclic(|weak_self: &Weak<RefCell<Self>>| {
             let cloned_weak_self = weak_self.clone();
             let on_message = Closure::new(move |event: MessageEvent| {
@@ -35,23 +43,58 @@ impl<P: Port + 'static> Server<P> {
                     return;
                 };
 
-                let data: Array = event.data().into();
-                let procedure = data.shift().as_string().unwrap();
+                if let Some(remote) = Handshake::from_js_value(&event.data()) {
+                    // The client reports the mismatch to its caller, but log it here too since
+                    // the server is usually the stale side
+                    if let Err(error) = handshake.check(remote) {
+                        log_error!("client handshake failed: {error}");
+                    }
 
-                if procedure == "*handshake" {
-                    if let Err(error) = server.port.post_message(&JsValue::from_str("*handshake")) {
+                    if let Err(error) = server.port.post_message(&handshake.to_js_value()) {
                         log_error!("error sending handshake: {error:?}");
                     }
-                } else {
-                    if let Err(error) = (server.dispatcher)(&procedure, data) {
-                        log_error!("error dispatching {procedure}: {error}");
+                    return;
+                }
+
+                let data: Array = event.data().into();
+                if Release::is_release(&data) {
+                    server.release();
+                    return;
+                }
+
+                match Batch::unbatch(&data) {
+                    Some((messages, stop_on_error)) => {
+                        let mut messages = messages.into_iter();
+                        for message in messages.by_ref() {
+                            if !server.dispatch(message) && stop_on_error {
+                                log_error!("skipping the rest of the batch after a failed call");
+                                break;
+                            }
+                        }
+
+                        // Answer the skipped calls so their callers aren't left waiting
+                        for message in messages {
+                            if let Ok(port) = message.at(-1).dyn_into::<MessagePort>() {
+                                fail(
+                                    &Error::CallFailed {
+                                        error: String::from(
+                                            "skipped after an earlier call in the batch failed",
+                                        ),
+                                    },
+                                    &port,
+                                );
+                            }
+                        }
+                    }
+                    None => {
+                        server.dispatch(data);
                     }
                 }
             });
 
             port.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
 
-            if let Err(error) = port.post_message(&JsValue::from_str("*handshake")) {
+            if let Err(error) = port.post_message(&handshake.to_js_value()) {
                 log_error!("error sending handshake: {error:?}");
             }
 
@@ -64,5 +107,105 @@ impl<P: Port + 'static> Server<P> {
         });
 
         server.borrow_mut().phylactery = Some(server.clone());
+
+        let revoker = Revoker::default();
+        let weak_server = Rc::downgrade(&server);
+        revoker.on_revoke(move || {
+            // The value may be revoked by one of its own methods, while the server is borrowed to
+            // dispatch the call, so wait for that to finish
+            spawn_local(async move {
+                let Some(server) = Weak::upgrade(&weak_server) else {
+                    return;
+                };
+
+                let Ok(mut server) = server.try_borrow_mut() else {
+                    log_error!("failed to borrow server to revoke it");
+                    return;
+                };
+
+                server.revoke();
+            });
+        });
+        revoker
+    }
+
+    /// Lets the client know the value was revoked and stops serving it. Calls that were already on
+    /// their way are never answered, so the client fails them itself.
+    fn revoke(&mut self) {
+        if let Err(error) = self.port.post_message(&Revoked::to_js_value()) {
+            log_error!("error posting revocation: {error:?}");
+        }
+        self.release();
+    }
+
+    /// Stops listening and lets go of the server. Dropping the server drops the dispatcher, which
+    /// holds the value being served.
+    fn release(&mut self) {
+        self.port.set_onmessage(None);
+        self.port.close();
+        self.phylactery = None;
+    }
+
+    /// Returns whether the message was dispatched successfully. Calls that fail to dispatch are
+    /// answered with the error.
+    fn dispatch(&mut self, data: Array) -> bool {
+        // The dispatcher consumes the message, so hold onto the response port in case it fails
+        let port = data.at(-1).dyn_into::<MessagePort>().ok();
+        let result = Procedure::from_js_value(data.shift())
+            .inspect_err(|error| {
+                log_error!("failed to read procedure in message callback: {error}");
+            })
+            .and_then(|procedure| {
+                (self.dispatcher)(&procedure, data)
+                    .inspect_err(|error| log_error!("error dispatching {procedure}: {error}"))
+            });
+
+        let Err(error) = result else {
+            return true;
+        };
+
+        if let Some(port) = port {
+            fail(&error, &port);
+        }
+        false
+    }
+}
+
+/// Posts the result of a procedure back to the caller. If the caller asked for the result to be
+/// pipelined, it's served on the caller's port instead and the caller is sent an acknowledgement.
+///
+/// # Errors
+///
+/// Fails if the result or the acknowledgement can't be converted or posted.
+pub fn respond<T: Post + Transfer>(
+    result: T,
+    port: &MessagePort,
+    pipeline: Option<MessagePort>,
+) -> Result<(), Error> {
+    let (value, transferable) = if let Some(pipeline) = pipeline {
+        let acknowledgement: Result<(), String> =
+            Pipeline::pipeline(result, pipeline).map_err(|error| error.to_string());
+        (Post::to_js_value(acknowledgement)?, None)
+    } else {
+        let value = Post::to_js_value(result)?;
+        let transferable = T::get_transferable(&value);
+        (value, transferable)
+    };
+
+    transferable
+        .map_or_else(
+            || port.post_message(&value),
+            |transferable| port.post_message_with_transferable(&value, &transferable),
+        )
+        .map_err(|error| Error::PostFailed {
+            error: format!("error while posting {}: {error:?}", type_name::<T>()),
+        })
+}
+
+/// Answers a call that couldn't be made with `error`, so that the caller fails rather than waiting
+/// for a result.
+pub fn fail(error: &Error, port: &MessagePort) {
+    if let Err(post_error) = port.post_message(&Failed::to_js_value(&error.to_string())) {
+        log_error!("error answering failed call with {error}: {post_error:?}");
     }
 }
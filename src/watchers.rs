@@ -0,0 +1,147 @@
+use std::cell::{Cell, RefCell};
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::{Rc, Weak};
+
+use wasm_bindgen_futures::spawn_local;
+
+use crate::{log_error, Error};
+
+type Watcher<T> = Box<dyn FnMut(&T) -> bool>;
+
+/// Callbacks watching fields of a proxied value.
+///
+/// The local wrapper checks them after each remote call that could have changed the value, and
+/// whenever the [`Notifier`] the value was served with is notified. A watcher is dropped once its
+/// callback fails.
+pub struct Watchers<T: ?Sized> {
+    watchers: Vec<Watcher<T>>,
+}
+
+impl<T: ?Sized> Default for Watchers<T> {
+    fn default() -> Self {
+        Self {
+            watchers: Vec::new(),
+        }
+    }
+}
+
+impl<T: ?Sized> Watchers<T> {
+    /// Calls `callback` with the current value of `field`, and again whenever it changes.
+    pub fn watch<F: Clone + PartialEq + 'static>(
+        &mut self,
+        local: &T,
+        field: impl Fn(&T) -> &F + 'static,
+        callback: impl Fn(F) -> Pin<Box<dyn Future<Output = Result<(), Error>>>> + 'static,
+    ) {
+        let mut last = field(local).clone();
+        let closed = Rc::new(Cell::new(false));
+        Self::send(callback(last.clone()), closed.clone());
+
+        self.watchers.push(Box::new(move |local| {
+            if closed.get() {
+                return false;
+            }
+
+            let value = field(local);
+            if *value != last {
+                last = value.clone();
+                Self::send(callback(last.clone()), closed.clone());
+            }
+            true
+        }));
+    }
+
+    /// Notifies the watchers of fields that changed since they were last checked.
+    pub fn check(&mut self, local: &T) {
+        self.watchers.retain_mut(|watcher| watcher(local));
+    }
+
+    fn send(future: Pin<Box<dyn Future<Output = Result<(), Error>>>>, closed: Rc<Cell<bool>>) {
+        spawn_local(async move {
+            if let Err(error) = future.await {
+                log_error!("failed to notify watcher: {error}");
+                closed.set(true);
+            }
+        });
+    }
+}
+
+type OnNotify = Box<dyn FnMut() -> bool>;
+
+/// Lets the owner of a proxied value tell watchers of its fields that it may have changed.
+///
+/// Watchers only see the changes remote calls make through the proxy on their own. Changes made
+/// any other way, like by the owner through a shared value, through interior mutability, or by a
+/// future that finishes after its call returned, are noticed once [`Notifier::notify`] is called.
+/// Clones notify the same values.
+#[derive(Clone, Default)]
+pub struct Notifier {
+    on_notify: Rc<RefCell<Vec<OnNotify>>>,
+}
+
+impl Notifier {
+    /// Checks the watchers of every value served with this notifier.
+    pub fn notify(&self) {
+        // Take the callbacks out first so that they're free to use the notifier
+        let Ok(mut on_notify) = self
+            .on_notify
+            .try_borrow_mut()
+            .map(|mut on_notify| std::mem::take(&mut *on_notify))
+        else {
+            log_error!("failed to borrow notifier to notify");
+            return;
+        };
+
+        on_notify.retain_mut(|on_notify| on_notify());
+
+        if let Ok(mut added) = self.on_notify.try_borrow_mut() {
+            on_notify.append(&mut added);
+            *added = on_notify;
+        } else {
+            log_error!("failed to borrow notifier to keep its callbacks");
+        }
+    }
+
+    /// Runs `check` on `wrapper` whenever this is notified, until the wrapper is dropped.
+    pub fn on_notify<W: 'static>(
+        &self,
+        wrapper: &Rc<RefCell<W>>,
+        check: impl Fn(&mut W) + 'static,
+    ) {
+        let wrapper = Rc::downgrade(wrapper);
+        let check = Rc::new(check);
+        let on_notify = move || {
+            let Some(upgraded) = Weak::upgrade(&wrapper) else {
+                return false;
+            };
+
+            if let Ok(mut upgraded) = upgraded.try_borrow_mut() {
+                check(&mut upgraded);
+                return true;
+            }
+
+            // Notified from within one of the value's calls, so check once it returns
+            let wrapper = wrapper.clone();
+            let check = check.clone();
+            spawn_local(async move {
+                let Some(wrapper) = Weak::upgrade(&wrapper) else {
+                    return;
+                };
+
+                if let Ok(mut wrapper) = wrapper.try_borrow_mut() {
+                    check(&mut wrapper);
+                } else {
+                    log_error!("failed to borrow value to check its watchers");
+                };
+            });
+            true
+        };
+
+        if let Ok(mut callbacks) = self.on_notify.try_borrow_mut() {
+            callbacks.push(Box::new(on_notify));
+        } else {
+            log_error!("failed to borrow notifier to add a value");
+        }
+    }
+}
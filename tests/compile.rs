@@ -0,0 +1,6 @@
+#[test]
+fn compile() {
+    let cases = trybuild::TestCases::new();
+    cases.pass("tests/compile/pass/*.rs");
+    cases.compile_fail("tests/compile/fail/*.rs");
+}
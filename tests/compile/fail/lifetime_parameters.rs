@@ -0,0 +1,8 @@
+use combadge::prelude::*;
+
+#[combadge]
+pub trait Borrowing<'a> {
+    fn name(&self) -> &'a str;
+}
+
+fn main() {}
@@ -0,0 +1,8 @@
+use combadge::prelude::*;
+
+#[combadge]
+pub trait Reserved<P> {
+    fn get(&self) -> P;
+}
+
+fn main() {}
@@ -0,0 +1,18 @@
+use combadge::prelude::*;
+
+#[combadge]
+pub trait Lifecycle {
+    fn init(&mut self);
+}
+
+#[combadge]
+pub trait Named: Lifecycle {
+    fn name(&self) -> String;
+}
+
+#[combadge]
+pub trait Feature: Named {
+    fn run(&mut self, steps: u32) -> u32;
+}
+
+fn main() {}
@@ -0,0 +1,14 @@
+use combadge::prelude::*;
+
+pub struct Widget {
+    size: u32,
+}
+
+#[proxy(part)]
+impl Widget {
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+}
+
+fn main() {}
@@ -0,0 +1,50 @@
+use combadge::prelude::*;
+use combadge::reexports::web_sys::MessagePort;
+use combadge::Error;
+
+#[combadge]
+pub trait Patterns {
+    fn ignored(&self, _: u32, port: u32, data: u32) -> u32;
+    fn tuple(&self, (a, b): (u32, u32)) -> u32 {
+        a + b
+    }
+    fn mutable(&mut self, mut value: u32) -> u32 {
+        value += 1;
+        value
+    }
+}
+
+pub struct Pattern;
+
+impl Patterns for Pattern {
+    fn ignored(&self, _: u32, port: u32, data: u32) -> u32 {
+        port + data
+    }
+}
+
+pub struct Point(u32, u32);
+
+#[proxy]
+impl Point {
+    pub fn offset(&self, (x, y): (u32, u32), _: String) -> (u32, u32) {
+        (self.0 + x, self.1 + y)
+    }
+
+    pub fn scale(&mut self, mut factor: u32) -> u32 {
+        factor *= 2;
+        self.0 *= factor;
+        self.0
+    }
+}
+
+pub async fn call(client: &mut PatternsClient<MessagePort>) -> Result<u32, Error> {
+    let _: u32 = client.ignored(1, 2, 3).await?;
+    let _: u32 = client.tuple((1, 2)).await?;
+    client.mutable(1).await
+}
+
+pub fn serve(port: MessagePort) {
+    PatternsServer::create(Pattern, port);
+}
+
+fn main() {}
@@ -0,0 +1,73 @@
+use std::future::Future;
+use std::pin::Pin;
+
+use combadge::prelude::*;
+use combadge::reexports::web_sys::MessagePort;
+use combadge::{Error, Local};
+
+#[combadge]
+pub trait Counter {
+    async fn add(&mut self, amount: u32) -> u32;
+    fn describe(&mut self) -> impl Future<Output = String>;
+    fn boxed(&mut self) -> Box<dyn Future<Output = u32>>;
+    fn pinned(&self) -> Pin<Box<dyn Future<Output = u32>>>;
+    fn sendable(&self) -> Pin<Box<dyn Future<Output = u32> + Send>>;
+    fn sendable_box(&self) -> Box<dyn Future<Output = u32> + Send + 'static>;
+    fn borrowing(&self) -> Pin<Box<dyn Future<Output = u32> + '_>>;
+}
+
+pub struct Count(u32);
+
+impl Counter for Count {
+    async fn add(&mut self, amount: u32) -> u32 {
+        self.0 += amount;
+        self.0
+    }
+
+    fn describe(&mut self) -> impl Future<Output = String> {
+        let count = self.0;
+        async move { count.to_string() }
+    }
+
+    fn boxed(&mut self) -> Box<dyn Future<Output = u32>> {
+        Box::new(std::future::ready(self.0))
+    }
+
+    fn pinned(&self) -> Pin<Box<dyn Future<Output = u32>>> {
+        Box::pin(std::future::ready(self.0))
+    }
+
+    fn sendable(&self) -> Pin<Box<dyn Future<Output = u32> + Send>> {
+        Box::pin(std::future::ready(self.0))
+    }
+
+    fn sendable_box(&self) -> Box<dyn Future<Output = u32> + Send + 'static> {
+        Box::new(std::future::ready(self.0))
+    }
+
+    fn borrowing(&self) -> Pin<Box<dyn Future<Output = u32> + '_>> {
+        Box::pin(async move { self.0 })
+    }
+}
+
+// Each method resolves to its future's output, whichever way the future is spelled
+pub async fn call(client: &mut CounterClient<MessagePort>) -> Result<u32, Error> {
+    let _: String = client.describe().await?;
+    let _: u32 = client.boxed().await?;
+    let _: u32 = client.pinned().await?;
+    let _: u32 = client.sendable().await?;
+    let _: u32 = client.sendable_box().await?;
+    let _: u32 = client.borrowing().await?;
+    client.add(1).await
+}
+
+pub async fn call_local(local: &mut Local<Count>) -> Result<u32, Error> {
+    let _: u32 = AsyncCounter::sendable(local).await?;
+    AsyncCounter::add(local, 1).await
+}
+
+pub fn serve(port: MessagePort) {
+    CounterServer::create(Count(0), port);
+}
+
+fn main() {}
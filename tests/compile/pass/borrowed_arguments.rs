@@ -0,0 +1,55 @@
+use std::future::Future;
+
+use combadge::prelude::*;
+use combadge::reexports::web_sys::MessagePort;
+use combadge::Error;
+
+#[combadge]
+pub trait Borrowed {
+    fn lookup(&mut self, key: &str) -> Option<u32>;
+    fn write(&mut self, data: &[u8]);
+    fn pair(&self, pair: &(u32, String)) -> u32;
+    async fn find(&self, key: &str) -> bool;
+    fn later(&self, key: &str) -> Box<dyn Future<Output = u32>>;
+}
+
+pub struct Table(Vec<(String, u32)>);
+
+impl Borrowed for Table {
+    fn lookup(&mut self, key: &str) -> Option<u32> {
+        self.0.iter().find(|(name, _)| name == key).map(|(_, value)| *value)
+    }
+
+    fn write(&mut self, data: &[u8]) {
+        self.0.push((String::from_utf8_lossy(data).into_owned(), 0));
+    }
+
+    fn pair(&self, pair: &(u32, String)) -> u32 {
+        pair.0
+    }
+
+    async fn find(&self, key: &str) -> bool {
+        self.0.iter().any(|(name, _)| name == key)
+    }
+
+    fn later(&self, key: &str) -> Box<dyn Future<Output = u32>> {
+        let length = key.len() as u32;
+        Box::new(async move { length })
+    }
+}
+
+// Borrowed arguments are only read while the call is being sent, so temporaries are fine
+pub async fn call(client: &mut BorrowedClient<MessagePort>) -> Result<bool, Error> {
+    let key = String::from("key");
+    let _: Option<u32> = client.lookup(&key).await?;
+    client.write(&[1, 2, 3]).await?;
+    let _: u32 = client.pair(&(1, String::from("one"))).await?;
+    let _: u32 = client.later("key").await?;
+    client.find("key").await
+}
+
+pub fn serve(port: MessagePort) {
+    BorrowedServer::create(Table(Vec::new()), port);
+}
+
+fn main() {}
@@ -0,0 +1,45 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use combadge::prelude::*;
+use combadge::reexports::web_sys::MessagePort;
+use combadge::Error;
+
+#[combadge]
+pub trait Renderer {
+    fn render(&self, frame: u32) -> String;
+    fn resize(&mut self, width: u32, height: u32);
+}
+
+pub struct Canvas;
+
+impl Renderer for Canvas {
+    fn render(&self, frame: u32) -> String {
+        format!("frame {frame}")
+    }
+
+    fn resize(&mut self, _width: u32, _height: u32) {}
+}
+
+// Handles to trait objects leave the remote side free of the implementation's type
+#[combadge]
+pub trait Studio {
+    fn renderer(&mut self) -> Handle<dyn Renderer>;
+    fn show(&self, renderer: Handle<dyn Renderer>);
+}
+
+pub fn canvas() -> Handle<dyn Renderer> {
+    let canvas: Rc<RefCell<dyn Renderer>> = Rc::new(RefCell::new(Canvas));
+    Handle::from(canvas)
+}
+
+pub async fn call(client: &mut StudioClient<MessagePort>) -> Result<String, Error> {
+    let mut renderer: RendererClient<MessagePort> = client.renderer().await?.try_into_client()?;
+    renderer.resize(1, 2).await?;
+    let pipelined: RendererClient<MessagePort> = client.renderer_pipelined()?;
+    let _: String = pipelined.render(1).await?;
+    client.show(canvas()).await?;
+    renderer.render(3).await
+}
+
+fn main() {}
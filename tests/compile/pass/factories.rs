@@ -0,0 +1,50 @@
+use combadge::reexports::web_sys::MessagePort;
+use combadge::{AsHandle, Error};
+
+mod scene {
+    use combadge::prelude::*;
+
+    pub struct Scene {
+        title: String,
+        objects: u32,
+    }
+
+    #[proxy]
+    impl Scene {
+        pub fn new(title: &str) -> Self {
+            Self {
+                title: title.to_string(),
+                objects: 0,
+            }
+        }
+
+        pub fn empty() -> Scene {
+            Self::new("")
+        }
+
+        pub fn title(&self) -> String {
+            self.title.clone()
+        }
+
+        pub fn add(&mut self) -> u32 {
+            self.objects += 1;
+            self.objects
+        }
+    }
+}
+
+use scene::{Scene, SceneFactoryClient, SceneFactoryLocal, SceneFactoryServer};
+
+// The factory is served and called from outside the module it's generated in
+pub fn serve(port: MessagePort) {
+    SceneFactoryServer::create(SceneFactoryLocal::default(), port);
+}
+
+pub async fn call(factory: &SceneFactoryClient<MessagePort>) -> Result<String, Error> {
+    let mut scene: <Scene as AsHandle<Scene>>::Client = factory.create_new_pipelined("title")?;
+    let _: u32 = scene.add().await?;
+    let _ = factory.create_empty().await?;
+    scene.title().await
+}
+
+fn main() {}
@@ -0,0 +1,71 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use combadge::prelude::*;
+use combadge::reexports::web_sys::MessagePort;
+use combadge::Error;
+
+#[combadge]
+pub trait Store<K, V: Clone>
+where
+    K: Hash,
+{
+    fn get(&self, key: &K) -> Option<V>;
+    fn set(&mut self, key: K, value: V);
+    fn convert<T: From<V>>(&self, key: K) -> T;
+    fn sized<const N: usize>(&self, data: [u8; N]) -> usize;
+    async fn load<T>(&self, key: K) -> Vec<T>;
+}
+
+pub struct MapStore(HashMap<String, u32>);
+
+impl Store<String, u32> for MapStore {
+    fn get(&self, key: &String) -> Option<u32> {
+        self.0.get(key).copied()
+    }
+
+    fn set(&mut self, key: String, value: u32) {
+        self.0.insert(key, value);
+    }
+
+    fn convert<T: From<u32>>(&self, key: String) -> T {
+        T::from(self.0[&key])
+    }
+
+    fn sized<const N: usize>(&self, _data: [u8; N]) -> usize {
+        N
+    }
+
+    async fn load<T>(&self, _key: String) -> Vec<T> {
+        Vec::new()
+    }
+}
+
+pub async fn call(client: &mut StoreClient<MessagePort, String, u32>) -> Result<usize, Error> {
+    let _: Option<u32> = client.get(&String::from("key")).await?;
+    client.set(String::from("key"), 1).await?;
+    let _: u64 = client.convert(String::from("key")).await?;
+    let _: Vec<u8> = client.load(String::from("key")).await?;
+    client.sized([0; 4]).await
+}
+
+// Servers are instantiated with every method's generic arguments, in declaration order
+pub fn serve(port: MessagePort) {
+    StoreServer::<_, String, u32, u64, 4, u8>::create(MapStore(HashMap::new()), port);
+}
+
+// Clients and servers only shake hands when they're instantiated with the same types
+fn main() {
+    assert_eq!(
+        StoreClient::<MessagePort, String, u32>::signature(),
+        StoreServer::<MessagePort, String, u32, u64, 4, u8>::signature()
+    );
+    assert_ne!(
+        StoreClient::<MessagePort, String, u32>::signature(),
+        StoreServer::<MessagePort, String, String, String, 4, u8>::signature()
+    );
+    assert_ne!(
+        StoreClient::<MessagePort, String, u32>::signature(),
+        StoreClient::<MessagePort, u32, u32>::signature()
+    );
+}
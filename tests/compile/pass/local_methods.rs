@@ -0,0 +1,62 @@
+use combadge::prelude::*;
+use combadge::reexports::web_sys::MessagePort;
+use combadge::{Error, Local};
+
+#[combadge]
+pub trait Counter {
+    const STEP: u32 = 2;
+
+    fn get(&self) -> u32;
+    async fn add(&mut self, amount: u32) -> u32;
+
+    /// Adds one step, unless the count is already past its limit.
+    #[combadge(local)]
+    fn step(&mut self) -> u32 {
+        let before = self.get();
+        if before > 100 {
+            return before;
+        }
+        self.add(Self::STEP);
+        self.get()
+    }
+
+    // Methods called through a path run on the client just the same
+    #[combadge(local)]
+    async fn step_twice(&mut self) -> u32 {
+        Self::step(self);
+        Counter::add(self, Self::STEP).await;
+        <Self as Counter>::get(self)
+    }
+}
+
+pub struct Count(u32);
+
+impl Counter for Count {
+    fn get(&self) -> u32 {
+        self.0
+    }
+
+    async fn add(&mut self, amount: u32) -> u32 {
+        self.0 += amount;
+        self.0
+    }
+}
+
+pub async fn call(client: &mut CounterClient<MessagePort>) -> Result<u32, Error> {
+    let _: u32 = client.step().await?;
+    client.step_twice().await
+}
+
+pub async fn generic(counter: &mut impl AsyncCounter) -> Result<u32, Error> {
+    counter.step_twice().await
+}
+
+pub async fn both(client: &mut CounterClient<MessagePort>, local: &mut Local<Count>) -> Result<u32, Error> {
+    Ok(generic(client).await? + generic(local).await?)
+}
+
+pub fn serve(port: MessagePort) {
+    CounterServer::create(Count(0), port);
+}
+
+fn main() {}
@@ -0,0 +1,77 @@
+use combadge::prelude::*;
+use combadge::reexports::web_sys::MessagePort;
+use combadge::{AsHandle, Error};
+
+mod shapes {
+    pub trait Area {
+        fn area(&self) -> u32;
+    }
+}
+
+pub struct Widget {
+    size: u32,
+    label: String,
+}
+
+// The main block lists the parts merged into its proxy
+#[proxy(parts(Labels, Area))]
+impl Widget {
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    pub fn grow(&mut self, by: u32) {
+        self.size += by;
+    }
+}
+
+#[proxy(part = Labels)]
+impl Widget {
+    pub fn label(&self) -> String {
+        self.label.clone()
+    }
+
+    pub fn relabel(&mut self, label: &str) {
+        self.label = label.to_string();
+    }
+}
+
+#[proxy(part)]
+impl shapes::Area for Widget {
+    fn area(&self) -> u32 {
+        self.size * self.size
+    }
+}
+
+pub async fn call(client: &mut <Widget as AsHandle<Widget>>::Client) -> Result<u32, Error> {
+    client.grow(1).await?;
+    client.relabel("widget").await?;
+    client.as_widget_labels_proxy_mut().relabel("part").await?;
+    let _: String = client.label().await?;
+    let _: u32 = client.as_widget_area_proxy().area().await?;
+    let _ = client.batch(|batch| (batch.area(), batch.label(), batch.size()));
+    client.area().await
+}
+
+// A trait impl can be proxied on its own too
+pub struct Gadget(u32);
+
+#[proxy]
+impl shapes::Area for Gadget {
+    fn area(&self) -> u32 {
+        self.0
+    }
+}
+
+#[combadge]
+pub trait Workshop {
+    fn widget(&mut self) -> Handle<Widget>;
+    fn gadget(&mut self) -> Handle<Gadget>;
+}
+
+pub async fn build(workshop: &mut WorkshopClient<MessagePort>) -> Result<u32, Error> {
+    let _: WidgetProxyClient<MessagePort> = workshop.widget().await?.try_into_client()?;
+    workshop.gadget().await?.try_into_client()?.area().await
+}
+
+fn main() {}
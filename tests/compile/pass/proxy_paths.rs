@@ -0,0 +1,83 @@
+use combadge::prelude::*;
+use combadge::reexports::web_sys::MessagePort;
+use combadge::{AsHandle, Error};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+mod model {
+    pub struct Scene {
+        pub objects: u32,
+    }
+
+    pub struct Cache<T> {
+        pub items: Vec<T>,
+    }
+
+    pub struct Concrete<T>(pub T);
+}
+
+// Generated items are named after the type, wherever it's defined
+#[proxy]
+impl crate::model::Scene {
+    pub fn objects(&self) -> u32 {
+        self.objects
+    }
+}
+
+#[proxy(parts(Sizes))]
+impl<T: Clone + Serialize + DeserializeOwned> model::Cache<T> {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            items: Vec::with_capacity(capacity),
+        }
+    }
+
+    pub fn first(&self) -> Option<T> {
+        self.items.first().cloned()
+    }
+
+    pub fn push(&mut self, item: T) {
+        self.items.push(item);
+    }
+}
+
+#[proxy(part = Sizes)]
+impl<T: Clone + Serialize + DeserializeOwned> model::Cache<T> {
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+}
+
+#[proxy]
+impl model::Concrete<u32> {
+    pub fn value(&self) -> u32 {
+        self.0
+    }
+}
+
+#[combadge]
+pub trait Library {
+    fn scene(&mut self) -> Handle<model::Scene>;
+    fn cache(&mut self) -> Handle<model::Cache<String>>;
+    fn concrete(&mut self) -> Handle<model::Concrete<u32>>;
+}
+
+pub async fn call(library: &mut LibraryClient<MessagePort>) -> Result<usize, Error> {
+    let _: u32 = library.scene().await?.try_into_client()?.objects().await?;
+    let _: u32 = library.concrete().await?.try_into_client()?.value().await?;
+    let mut cache = library.cache().await?.try_into_client()?;
+    cache.push(String::from("item")).await?;
+    let _: Option<String> = cache.first().await?;
+    cache.len().await
+}
+
+pub async fn create(factory: &CacheFactoryClient<MessagePort, u8>) -> Result<Option<u8>, Error> {
+    let cache: <model::Cache<u8> as AsHandle<model::Cache<u8>>>::Client = factory.create_new_pipelined(4)?;
+    cache.first().await
+}
+
+pub fn serve(port: MessagePort) {
+    CacheFactoryServer::<_, u8>::create(CacheFactoryLocal::default(), port);
+}
+
+fn main() {}
@@ -0,0 +1,55 @@
+use std::rc::Rc;
+
+use combadge::prelude::*;
+use combadge::reexports::web_sys::MessagePort;
+use combadge::Error;
+
+#[combadge]
+pub trait Settings {
+    const LIMIT: u32;
+    const NAME: &'static str = "settings";
+    type Extra;
+    type View<'a>: AsRef<[u8]>
+    where
+        Self: 'a;
+
+    fn limit(&self) -> u32;
+    async fn name(&self) -> String;
+}
+
+pub struct Defaults;
+
+impl Settings for Defaults {
+    const LIMIT: u32 = 4;
+    const NAME: &'static str = "defaults";
+    type Extra = Vec<u8>;
+    type View<'a> = &'a [u8];
+
+    fn limit(&self) -> u32 {
+        Self::LIMIT
+    }
+
+    async fn name(&self) -> String {
+        Self::NAME.to_string()
+    }
+}
+
+// Shared values keep their implementation's associated items
+const _: () = assert!(<Rc<Defaults> as Settings>::LIMIT == 4);
+const _: () = assert!(matches!(<Rc<Defaults> as Settings>::NAME.as_bytes(), b"defaults"));
+const _: fn(<Rc<Defaults> as Settings>::Extra) -> Vec<u8> = |extra| extra;
+
+pub fn view<'a>(view: <Rc<Defaults> as Settings>::View<'a>) -> &'a [u8] {
+    view
+}
+
+pub async fn call(client: &SettingsClient<MessagePort>) -> Result<String, Error> {
+    let _: u32 = client.limit().await?;
+    client.name().await
+}
+
+pub fn serve(port: MessagePort) {
+    SettingsServer::create(Rc::new(Defaults), port);
+}
+
+fn main() {}
@@ -0,0 +1,61 @@
+use combadge::prelude::*;
+use combadge::reexports::web_sys::MessagePort;
+use combadge::{Error, Local};
+
+#[combadge]
+pub trait Lifecycle {
+    fn init(&mut self);
+}
+
+#[combadge]
+pub trait Named: Lifecycle {
+    fn name(&self) -> String;
+}
+
+// Supertraits' supertraits are listed too, so that their methods are routed
+#[combadge]
+pub trait Feature: Named + Lifecycle + 'static {
+    fn run(&mut self, steps: u32) -> u32;
+}
+
+pub struct Thing;
+
+impl Lifecycle for Thing {
+    fn init(&mut self) {}
+}
+
+impl Named for Thing {
+    fn name(&self) -> String {
+        String::from("thing")
+    }
+}
+
+impl Feature for Thing {
+    fn run(&mut self, steps: u32) -> u32 {
+        steps
+    }
+}
+
+pub async fn call(client: &mut FeatureClient<MessagePort>) -> Result<u32, Error> {
+    client.init().await?;
+    let _: String = client.name().await?;
+    let _: String = client.as_named().name().await?;
+    client.run(1).await
+}
+
+// Each mirror extends its supertraits' mirrors, so generic code reaches every level
+pub async fn generic(feature: &mut impl AsyncFeature) -> Result<u32, Error> {
+    feature.init().await?;
+    let _: String = feature.name().await?;
+    feature.run(1).await
+}
+
+pub async fn both(client: &mut FeatureClient<MessagePort>, local: &mut Local<Thing>) -> Result<u32, Error> {
+    Ok(generic(client).await? + generic(local).await?)
+}
+
+pub fn serve(port: MessagePort) {
+    FeatureServer::create(Thing, port);
+}
+
+fn main() {}